@@ -0,0 +1,295 @@
+use std::{cmp::Ordering, collections::BTreeSet};
+
+use anyhow::{anyhow, Result};
+
+use crate::{ast::Expr, eval, page::Column, value::Value};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Count,
+    Sum,
+    Total,
+    Avg,
+    Min,
+    Max,
+    GroupConcat,
+}
+
+#[derive(Debug)]
+enum State {
+    Count(i64),
+    Sum {
+        count: i64,
+        // exact sum while all values are integers
+        int: Option<i64>,
+        overflow: bool,
+        // compensated sum of all values as reals
+        real: f64,
+        compensation: f64,
+    },
+    // the smallest or largest value so far
+    Extreme(Option<Column>),
+    Concat(Option<String>),
+}
+
+/// The running result of one aggregate function call over the rows of a group.
+#[derive(Debug)]
+pub struct Accumulator {
+    function: Function,
+    // values already seen by a DISTINCT aggregate
+    seen: Option<BTreeSet<DistinctValue>>,
+    state: State,
+}
+
+impl Accumulator {
+    /// Starts an aggregate for `call`, checking its arguments.
+    pub fn new(call: &Expr) -> Result<Accumulator> {
+        let Expr::Function {
+            name,
+            distinct,
+            args,
+        } = call
+        else {
+            return Err(anyhow!("not a function call: {call:?}"));
+        };
+
+        let (function, arities) = match name.as_str() {
+            "count" => (Function::Count, 0..=1),
+            "sum" => (Function::Sum, 1..=1),
+            "total" => (Function::Total, 1..=1),
+            "avg" => (Function::Avg, 1..=1),
+            "min" => (Function::Min, 1..=1),
+            "max" => (Function::Max, 1..=1),
+            "group_concat" => (Function::GroupConcat, 1..=2),
+            "string_agg" => (Function::GroupConcat, 2..=2),
+            _ => return Err(anyhow!("no such function: {name}")),
+        };
+        if !arities.contains(&args.len()) {
+            return Err(anyhow!("wrong number of arguments to function {name}()"));
+        }
+        if *distinct && args.len() != 1 {
+            return Err(anyhow!(
+                "DISTINCT aggregates must have exactly one argument"
+            ));
+        }
+
+        let state = match function {
+            Function::Count => State::Count(0),
+            Function::Sum | Function::Total | Function::Avg => State::Sum {
+                count: 0,
+                int: Some(0),
+                overflow: false,
+                real: 0.0,
+                compensation: 0.0,
+            },
+            Function::Min | Function::Max => State::Extreme(None),
+            Function::GroupConcat => State::Concat(None),
+        };
+
+        Ok(Accumulator {
+            function,
+            seen: distinct.then(BTreeSet::new),
+            state,
+        })
+    }
+
+    /// Adds the argument values of one row. Returns true if MIN or MAX took its value
+    /// from this row, or hasn't found any value yet.
+    pub fn step(&mut self, args: &[Column]) -> bool {
+        // count(*) counts every row, the others skip NULLs
+        let Some(value) = args.first() else {
+            if let State::Count(count) = &mut self.state {
+                *count += 1;
+            }
+            return false;
+        };
+        if *value == Column::Null {
+            return matches!(self.state, State::Extreme(None));
+        }
+        if let Some(seen) = &mut self.seen {
+            if !seen.insert(DistinctValue(value.clone())) {
+                return false;
+            }
+        }
+
+        match &mut self.state {
+            State::Count(count) => *count += 1,
+            State::Sum {
+                count,
+                int,
+                overflow,
+                real,
+                compensation,
+            } => {
+                *count += 1;
+                let value = eval::numeric_value(value.clone());
+                match (value.as_i64(), *int) {
+                    (Some(i), Some(sum)) => match sum.checked_add(i) {
+                        Some(sum) => *int = Some(sum),
+                        None => *overflow = true,
+                    },
+                    (None, _) => *int = None,
+                    (Some(_), None) => {}
+                }
+                // Neumaier summation keeps the error of adding reals small
+                let x = value.as_f64().unwrap_or(0.0);
+                let t = *real + x;
+                *compensation += match real.abs() >= x.abs() {
+                    true => (*real - t) + x,
+                    false => (x - t) + *real,
+                };
+                *real = t;
+            }
+            State::Extreme(extreme) => {
+                let wanted = match self.function {
+                    Function::Min => Ordering::Less,
+                    _ => Ordering::Greater,
+                };
+                if extreme
+                    .as_ref()
+                    .map_or(true, |e| value.compare(e) == wanted)
+                {
+                    *extreme = Some(value.clone());
+                    return true;
+                }
+            }
+            State::Concat(text) => {
+                let separator = match args.get(1) {
+                    Some(Column::Null) => String::new(),
+                    Some(separator) => Value::from(separator.clone()).to_string(),
+                    None => ",".to_string(),
+                };
+                let value = Value::from(value.clone()).to_string();
+                match text {
+                    Some(text) => {
+                        text.push_str(&separator);
+                        text.push_str(&value);
+                    }
+                    None => *text = Some(value),
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Returns the result of the aggregate over the rows added so far.
+    pub fn finish(self) -> Result<Column> {
+        Ok(match self.state {
+            State::Count(count) => Column::I64(count),
+            State::Sum {
+                count,
+                int,
+                overflow,
+                real,
+                compensation,
+            } => match self.function {
+                Function::Sum if count == 0 => Column::Null,
+                Function::Sum if int.is_some() && overflow => {
+                    return Err(anyhow!("integer overflow"))
+                }
+                Function::Sum if int.is_some() => Column::I64(int.unwrap()),
+                Function::Avg if count == 0 => Column::Null,
+                Function::Avg => Column::F64((real + compensation) / count as f64),
+                _ => Column::F64(real + compensation),
+            },
+            State::Extreme(extreme) => extreme.unwrap_or(Column::Null),
+            State::Concat(text) => text.map_or(Column::Null, Column::Str),
+        })
+    }
+}
+
+/// A value ordered the way SQLite compares values, so that 1 and 1.0 are the same.
+#[derive(Debug)]
+struct DistinctValue(Column);
+
+impl PartialEq for DistinctValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.compare(&other.0) == Ordering::Equal
+    }
+}
+
+impl Eq for DistinctValue {}
+
+impl PartialOrd for DistinctValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DistinctValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.compare(&other.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::{ResultColumn, Statement},
+        parser,
+        query::Affinity,
+    };
+
+    /// Runs `call` over rows that have a single column `a` with the given values.
+    fn aggregate(call: &str, values: &[Column]) -> Result<Column> {
+        let Ok(Statement::Select(select)) = parser::parse(&format!("select {call} from t")) else {
+            panic!("can't parse {call}");
+        };
+        let ResultColumn::Expr {
+            expr: expr @ Expr::Function { args, .. },
+            ..
+        } = &select.columns[0]
+        else {
+            panic!("not a function call");
+        };
+
+        let mut accumulator = Accumulator::new(expr)?;
+        for value in values {
+            let row = |_: &str| Some((value.clone(), Affinity::Blob));
+            let args = args
+                .iter()
+                .map(|arg| eval::eval(arg, &row))
+                .collect::<Result<Vec<_>>>()?;
+            accumulator.step(&args);
+        }
+        accumulator.finish()
+    }
+
+    #[test]
+    fn it_works() {
+        let rows = &[
+            Column::I8(1),
+            Column::Null,
+            Column::F64(1.0),
+            Column::Str("4".to_string()),
+        ];
+        assert_eq!(aggregate("count(*)", rows).unwrap(), Column::I64(4));
+        assert_eq!(aggregate("count(a)", rows).unwrap(), Column::I64(3));
+        assert_eq!(
+            aggregate("count(distinct a)", rows).unwrap(),
+            Column::I64(2)
+        );
+        assert_eq!(aggregate("sum(a)", rows).unwrap(), Column::F64(6.0));
+        assert_eq!(aggregate("avg(a)", rows).unwrap(), Column::F64(2.0));
+        assert_eq!(
+            aggregate("max(a)", rows).unwrap(),
+            Column::Str("4".to_string())
+        );
+        assert_eq!(
+            aggregate("group_concat(a, '-')", rows).unwrap(),
+            Column::Str("1-1.0-4".to_string())
+        );
+    }
+
+    #[test]
+    fn handles_null() {
+        let rows = &[Column::Null];
+        assert_eq!(aggregate("sum(a)", rows).unwrap(), Column::Null);
+        assert_eq!(aggregate("total(a)", rows).unwrap(), Column::F64(0.0));
+        assert_eq!(aggregate("min(a)", &[]).unwrap(), Column::Null);
+        assert_eq!(aggregate("count(a)", rows).unwrap(), Column::I64(0));
+        assert!(aggregate("sum(a, b)", rows).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Select(Box<Select>),
+    CreateTable(CreateTable),
+    CreateIndex(CreateIndex),
+    CreateView(CreateView),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Select {
+    pub columns: Vec<ResultColumn>,
+    pub from: TableRef,
+    pub joins: Vec<Join>,
+    pub where_clause: Option<Expr>,
+    pub group_by: Vec<Expr>,
+    pub having: Option<Expr>,
+    pub order_by: Vec<OrderingTerm>,
+    pub limit: Option<Expr>,
+    pub offset: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableRef {
+    pub name: String,
+    pub alias: Option<String>,
+}
+
+impl TableRef {
+    /// Returns the name that qualifies the columns of the table.
+    pub fn qualifier(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Join {
+    pub kind: JoinKind,
+    pub table: TableRef,
+    pub on: Option<Expr>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JoinKind {
+    Inner,
+    Left,
+    // a comma or CROSS JOIN
+    Cross,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderingTerm {
+    pub expr: Expr,
+    pub descending: bool,
+    // where NULLs go if given, by default they sort as the smallest values
+    pub nulls: Option<NullsOrder>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NullsOrder {
+    First,
+    Last,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResultColumn {
+    // *
+    All,
+    Expr { expr: Expr, alias: Option<String> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Column {
+        // the table name or alias in table.column
+        table: Option<String>,
+        name: String,
+    },
+    Literal(Literal),
+    Not(Box<Expr>),
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    Function {
+        // lowercase
+        name: String,
+        distinct: bool,
+        // empty for count(*)
+        args: Vec<Expr>,
+    },
+}
+
+impl Expr {
+    /// Returns an unqualified column.
+    pub fn column(name: &str) -> Expr {
+        Expr::Column {
+            table: None,
+            name: name.to_string(),
+        }
+    }
+
+    /// Returns a copy of the expression with every column replaced by the expression
+    /// `replace` returns for its table qualifier and name.
+    pub fn map_columns<F>(&self, replace: &F) -> anyhow::Result<Expr>
+    where
+        F: Fn(Option<&str>, &str) -> anyhow::Result<Expr>,
+    {
+        Ok(match self {
+            Expr::Column { table, name } => replace(table.as_deref(), name)?,
+            Expr::Literal(literal) => Expr::Literal(literal.clone()),
+            Expr::Not(expr) => Expr::Not(Box::new(expr.map_columns(replace)?)),
+            Expr::Binary { op, lhs, rhs } => Expr::Binary {
+                op: *op,
+                lhs: Box::new(lhs.map_columns(replace)?),
+                rhs: Box::new(rhs.map_columns(replace)?),
+            },
+            Expr::Function {
+                name,
+                distinct,
+                args,
+            } => Expr::Function {
+                name: name.to_string(),
+                distinct: *distinct,
+                args: args
+                    .iter()
+                    .map(|arg| arg.map_columns(replace))
+                    .collect::<anyhow::Result<_>>()?,
+            },
+        })
+    }
+
+    /// Calls `f` on the expression and on each of its subexpressions, parents first.
+    /// Arguments of a call are skipped if `f` returns false for it.
+    pub fn visit<'a, F>(&'a self, f: &mut F)
+    where
+        F: FnMut(&'a Expr) -> bool,
+    {
+        if !f(self) {
+            return;
+        }
+        match self {
+            Expr::Column { .. } | Expr::Literal(_) => {}
+            Expr::Not(expr) => expr.visit(f),
+            Expr::Binary { lhs, rhs, .. } => {
+                lhs.visit(f);
+                rhs.visit(f);
+            }
+            Expr::Function { args, .. } => args.iter().for_each(|arg| arg.visit(f)),
+        }
+    }
+
+    /// Splits the expression into the terms joined by its top level ANDs.
+    pub fn conjuncts(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Binary {
+                op: BinaryOp::And,
+                lhs,
+                rhs,
+            } => {
+                let mut res = lhs.conjuncts();
+                res.append(&mut rhs.conjuncts());
+                res
+            }
+            expr => vec![expr],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    // case insensitive for ASCII letters, with % and _ as wildcards
+    Like,
+    // like = and != but NULL is equal to NULL
+    Is,
+    IsNot,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    /// Returns the operator that gives the same result with the operands swapped.
+    pub fn swapped(self) -> BinaryOp {
+        match self {
+            BinaryOp::Lt => BinaryOp::Gt,
+            BinaryOp::Le => BinaryOp::Ge,
+            BinaryOp::Gt => BinaryOp::Lt,
+            BinaryOp::Ge => BinaryOp::Le,
+            op => op,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateTable {
+    pub name: String,
+    pub columns: Vec<ColumnDef>,
+    pub constraints: Vec<TableConstraint>,
+    pub without_rowid: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnDef {
+    pub name: String,
+    // type name words as written, empty if the column has no type
+    pub type_name: String,
+    pub constraints: Vec<ColumnConstraint>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnConstraint {
+    PrimaryKey { descending: bool },
+    Unique,
+    // the collating sequence of the column
+    Collate(String),
+    // NOT NULL, CHECK, DEFAULT, REFERENCES and generated columns
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableConstraint {
+    PrimaryKey(Vec<IndexedColumn>),
+    Unique(Vec<IndexedColumn>),
+    // CHECK and FOREIGN KEY
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateIndex {
+    pub name: String,
+    pub table_name: String,
+    pub columns: Vec<IndexedColumn>,
+    // the condition of a partial index, which only has entries for the rows it holds for
+    pub where_clause: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedColumn {
+    pub name: String,
+    pub descending: bool,
+    // the collating sequence given by COLLATE, if any
+    pub collation: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateView {
+    pub name: String,
+    pub columns: Option<Vec<String>>,
+    // the text of the SELECT after AS
+    pub select_sql: String,
+}
@@ -0,0 +1,499 @@
+use std::rc::Rc;
+
+use anyhow::Result;
+
+use crate::{
+    db::Db,
+    page::{Column, Page, PageType},
+};
+
+/// The key of a b-tree entry: the rowid in a table b-tree, the record in an index
+/// b-tree.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key<'a> {
+    Rowid(i64),
+    Record(&'a [Column]),
+}
+
+/// Walks the entries of a table or index b-tree in key order, either way, one page
+/// per level kept in memory.
+pub struct BTreeCursor<'a> {
+    db: &'a Db,
+    root_page_num: u32,
+    // Pages from the root down to the current entry, with the slot taken in each
+    stack: Vec<(Rc<Page>, usize)>,
+}
+
+impl<'a> BTreeCursor<'a> {
+    /// Returns a cursor on the first entry of the b-tree rooted at `root_page_num`.
+    pub fn first(db: &'a Db, root_page_num: u32) -> Result<Self> {
+        Self::seek_ge(db, root_page_num, |_| false)
+    }
+
+    /// Returns a cursor on the last entry of the b-tree rooted at `root_page_num`.
+    pub fn last(db: &'a Db, root_page_num: u32) -> Result<Self> {
+        Self::seek_le(db, root_page_num, |_| false)
+    }
+
+    /// Returns a cursor on the first entry whose key `is_before` is false for, which
+    /// must hold for the keys up to some point and not after it.
+    pub fn seek_ge(
+        db: &'a Db,
+        root_page_num: u32,
+        is_before: impl Fn(Key) -> bool,
+    ) -> Result<Self> {
+        let mut cursor = Self::root(db, root_page_num)?;
+        cursor.descend_ge(&is_before)?;
+        Ok(cursor)
+    }
+
+    /// Returns a cursor on the last entry whose key `is_after` is false for, which
+    /// must hold for the keys from some point on and not before it.
+    pub fn seek_le(db: &'a Db, root_page_num: u32, is_after: impl Fn(Key) -> bool) -> Result<Self> {
+        let mut cursor = Self::root(db, root_page_num)?;
+        cursor.descend_le(&is_after)?;
+        Ok(cursor)
+    }
+
+    /// Moves to the first entry whose key `is_before` is false for, like `seek_ge`
+    /// but only reading again the pages whose keys `is_before` or `is_after` tell
+    /// can't hold it.
+    pub fn reseek_ge(
+        &mut self,
+        is_before: impl Fn(Key) -> bool,
+        is_after: impl Fn(Key) -> bool,
+    ) -> Result<()> {
+        while self.stack.len() > 1 {
+            let (page, _) = self.stack.last().unwrap();
+            let (first, last) = Self::first_last_keys(page);
+            if first.is_some_and(|key| !is_after(key)) && last.is_some_and(|key| !is_before(key)) {
+                break;
+            }
+            self.stack.pop();
+        }
+        // a cursor past either end has let go of its root
+        if self.stack.is_empty() {
+            *self = Self::root(self.db, self.root_page_num)?;
+        }
+        self.descend_ge(&is_before)
+    }
+
+    /// Counts the entries of the b-tree rooted at `root_page_num`. Only the headers
+    /// of its leaf pages are read, their records are never decoded.
+    pub fn count(db: &'a Db, root_page_num: u32) -> Result<i64> {
+        let mut cursor = Self::root(db, root_page_num)?;
+        let mut count = 0;
+        // below the root the stack only holds interior pages, at the slot to count next
+        while let Some((page, slot)) = cursor.stack.last_mut() {
+            if *slot >= Self::num_slots(page) {
+                cursor.stack.pop();
+                continue;
+            }
+            let child = Self::child(page, *slot);
+            *slot += 1;
+            let Some(page_num) = child else {
+                // a cell of an index interior page or of a leaf root
+                count += 1;
+                continue;
+            };
+            let page_header = db.page_header(page_num)?;
+            match page_header.page_type {
+                PageType::LeafTable | PageType::LeafIndex => {
+                    count += i64::from(page_header.num_cells)
+                }
+                PageType::InteriorTable | PageType::InteriorIndex => cursor.push(page_num)?,
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Returns the rowid (for table b-trees) and the record of the current entry,
+    /// or `None` once the cursor has moved past either end.
+    pub fn entry(&self) -> Option<(Option<i64>, &[Column])> {
+        let (page, slot) = self.stack.last()?;
+        match page.as_ref() {
+            Page::LeafTable(p) => p
+                .cells
+                .get(*slot)
+                .map(|c| (Some(c.rowid), c.record_body.columns.as_slice())),
+            Page::LeafIndex(p) => p
+                .cells
+                .get(*slot)
+                .map(|c| (None, c.record_body.columns.as_slice())),
+            // odd slots of an index interior page are its cells
+            Page::InteriorIdx(p) if slot % 2 == 1 => p
+                .cells
+                .get(slot / 2)
+                .map(|c| (None, c.record_body.columns.as_slice())),
+            _ => None,
+        }
+    }
+
+    /// Returns the key of the current entry.
+    pub fn key(&self) -> Option<Key<'_>> {
+        self.entry().map(|(rowid, record)| match rowid {
+            Some(rowid) => Key::Rowid(rowid),
+            None => Key::Record(record),
+        })
+    }
+
+    /// Moves to the next entry in key order.
+    pub fn next(&mut self) -> Result<()> {
+        while let Some((page, slot)) = self.stack.last_mut() {
+            *slot += 1;
+            if *slot >= Self::num_slots(page) {
+                self.stack.pop();
+                continue;
+            }
+            self.descend_first()?;
+            if self.entry().is_some() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Moves to the previous entry in key order.
+    pub fn prev(&mut self) -> Result<()> {
+        while let Some((_, slot)) = self.stack.last_mut() {
+            if *slot == 0 {
+                self.stack.pop();
+                continue;
+            }
+            *slot -= 1;
+            self.descend_last()?;
+            if self.entry().is_some() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn root(db: &'a Db, root_page_num: u32) -> Result<Self> {
+        Ok(BTreeCursor {
+            db,
+            root_page_num,
+            stack: vec![(db.page(root_page_num)?, 0)],
+        })
+    }
+
+    /// Returns the keys of the first and last cells of a page.
+    fn first_last_keys(page: &Page) -> (Option<Key<'_>>, Option<Key<'_>>) {
+        match page {
+            Page::LeafTable(p) => (
+                p.cells.first().map(|c| Key::Rowid(c.rowid)),
+                p.cells.last().map(|c| Key::Rowid(c.rowid)),
+            ),
+            Page::InteriorTable(p) => (
+                p.cells.first().map(|c| Key::Rowid(c.rowid)),
+                p.cells.last().map(|c| Key::Rowid(c.rowid)),
+            ),
+            Page::LeafIndex(p) => (
+                p.cells.first().map(|c| Key::Record(&c.record_body.columns)),
+                p.cells.last().map(|c| Key::Record(&c.record_body.columns)),
+            ),
+            Page::InteriorIdx(p) => (
+                p.cells.first().map(|c| Key::Record(&c.record_body.columns)),
+                p.cells.last().map(|c| Key::Record(&c.record_body.columns)),
+            ),
+        }
+    }
+
+    /// Follows the child at the current slot down to its first entry.
+    fn descend_first(&mut self) -> Result<()> {
+        while let Some(page_num) = self.current_child() {
+            self.push(page_num)?;
+        }
+
+        Ok(())
+    }
+
+    /// Follows the child at the current slot down to its last entry.
+    fn descend_last(&mut self) -> Result<()> {
+        while let Some(page_num) = self.current_child() {
+            self.push(page_num)?;
+            let (page, slot) = self.stack.last_mut().unwrap();
+            *slot = Self::num_slots(page).saturating_sub(1);
+        }
+
+        Ok(())
+    }
+
+    /// Goes down from the page on top of the stack to the first entry `is_before`
+    /// is false for.
+    fn descend_ge(&mut self, is_before: &impl Fn(Key) -> bool) -> Result<()> {
+        loop {
+            let (page, slot) = self.stack.last_mut().unwrap();
+            // the first entry not before is under or at the first key not before
+            let first = Self::partition(page, is_before);
+            *slot = match page.as_ref() {
+                Page::InteriorIdx(_) => 2 * first,
+                _ => first,
+            };
+            match self.current_child() {
+                Some(page_num) => self.push(page_num)?,
+                None => break,
+            }
+        }
+
+        // the entries under the child taken may all be before
+        if self.entry().is_none() {
+            self.next()?;
+        }
+        Ok(())
+    }
+
+    /// Goes down from the page on top of the stack to the last entry `is_after` is
+    /// false for.
+    fn descend_le(&mut self, is_after: &impl Fn(Key) -> bool) -> Result<()> {
+        let not_after = |key: Key| !is_after(key);
+        loop {
+            let (page, slot) = self.stack.last_mut().unwrap();
+            // the last entry not after is under the first key after, or before it
+            let first_after = Self::partition(page, &not_after);
+            *slot = match page.as_ref() {
+                Page::InteriorIdx(_) => 2 * first_after,
+                Page::InteriorTable(_) => first_after,
+                _ => first_after.saturating_sub(1),
+            };
+            match self.current_child() {
+                Some(page_num) => self.push(page_num)?,
+                // when every entry of the leaf is after, step back from the first
+                None if first_after == 0 => return self.prev(),
+                None => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of keys of a page for which `pred` holds, a prefix of them.
+    fn partition(page: &Page, pred: &impl Fn(Key) -> bool) -> usize {
+        match page {
+            Page::LeafTable(p) => p.cells.partition_point(|c| pred(Key::Rowid(c.rowid))),
+            Page::InteriorTable(p) => p.cells.partition_point(|c| pred(Key::Rowid(c.rowid))),
+            Page::LeafIndex(p) => p
+                .cells
+                .partition_point(|c| pred(Key::Record(&c.record_body.columns))),
+            Page::InteriorIdx(p) => p
+                .cells
+                .partition_point(|c| pred(Key::Record(&c.record_body.columns))),
+        }
+    }
+
+    /// Reads a child page and puts it on the stack at its first slot.
+    fn push(&mut self, page_num: u32) -> Result<()> {
+        self.stack.push((self.db.page(page_num)?, 0));
+        Ok(())
+    }
+
+    /// Returns the page number of the child at the current slot, if it holds one.
+    fn current_child(&self) -> Option<u32> {
+        let (page, slot) = self.stack.last()?;
+        Self::child(page, *slot)
+    }
+
+    /// Returns the page number of the child at `slot` of a page, if it holds one.
+    fn child(page: &Page, slot: usize) -> Option<u32> {
+        let (cell, rightmost) = match page {
+            Page::InteriorTable(p) => (
+                p.cells.get(slot).map(|c| c.left_child_page_num),
+                p.page_header.rightmost_pointer,
+            ),
+            Page::InteriorIdx(p) if slot % 2 == 0 => (
+                p.cells.get(slot / 2).map(|c| c.left_child_page_num),
+                p.page_header.rightmost_pointer,
+            ),
+            _ => return None,
+        };
+        cell.or(rightmost)
+    }
+
+    /// Interior table pages have a slot per child, index interior pages alternate
+    /// children and cells, and leaf pages have a slot per cell.
+    fn num_slots(page: &Page) -> usize {
+        match page {
+            Page::LeafTable(p) => p.cells.len(),
+            Page::LeafIndex(p) => p.cells.len(),
+            Page::InteriorTable(p) => p.cells.len() + 1,
+            Page::InteriorIdx(p) => 2 * p.cells.len() + 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use super::*;
+
+    fn rowids(cursor: &mut BTreeCursor, forward: bool) -> Vec<i64> {
+        let mut rowids = vec![];
+        while let Some((Some(rowid), _)) = cursor.entry() {
+            rowids.push(rowid);
+            match forward {
+                true => cursor.next().unwrap(),
+                false => cursor.prev().unwrap(),
+            }
+        }
+        rowids
+    }
+
+    #[test]
+    fn it_works() {
+        let db = Db::new(File::open("sample.db").unwrap()).unwrap();
+        let root_page_num = db.table_infos["apples"].root_page_num;
+
+        let mut cursor = BTreeCursor::first(&db, root_page_num).unwrap();
+        assert_eq!(
+            cursor.entry().unwrap().1[1],
+            Column::Str("Granny Smith".to_string())
+        );
+        assert_eq!(rowids(&mut cursor, true), [1, 2, 3, 4]);
+
+        let mut cursor = BTreeCursor::last(&db, root_page_num).unwrap();
+        assert_eq!(rowids(&mut cursor, false), [4, 3, 2, 1]);
+
+        assert_eq!(BTreeCursor::count(&db, root_page_num).unwrap(), 4);
+    }
+
+    #[test]
+    fn seeks_keys() {
+        let db = Db::new(File::open("sample.db").unwrap()).unwrap();
+        let root_page_num = db.table_infos["apples"].root_page_num;
+        let before = |rowid| move |key: Key| matches!(key, Key::Rowid(r) if r < rowid);
+        let after = |rowid| move |key: Key| matches!(key, Key::Rowid(r) if r > rowid);
+
+        let mut cursor = BTreeCursor::seek_ge(&db, root_page_num, before(3)).unwrap();
+        assert_eq!(cursor.key(), Some(Key::Rowid(3)));
+        cursor.prev().unwrap();
+        assert_eq!(cursor.key(), Some(Key::Rowid(2)));
+        cursor.reseek_ge(before(4), after(4)).unwrap();
+        assert_eq!(cursor.key(), Some(Key::Rowid(4)));
+        cursor.reseek_ge(before(1), after(1)).unwrap();
+        assert_eq!(cursor.key(), Some(Key::Rowid(1)));
+
+        let cursor = BTreeCursor::seek_le(&db, root_page_num, after(0)).unwrap();
+        assert_eq!(cursor.key(), None);
+        let cursor = BTreeCursor::seek_le(&db, root_page_num, after(9)).unwrap();
+        assert_eq!(cursor.key(), Some(Key::Rowid(4)));
+        let cursor = BTreeCursor::seek_ge(&db, root_page_num, before(9)).unwrap();
+        assert_eq!(cursor.key(), None);
+    }
+
+    #[test]
+    fn walks_tables_of_several_levels() {
+        // deep has a root above interior pages above the leaves of its 800 rows
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        let root_page_num = db.table_infos["deep"].root_page_num;
+        let before = |rowid| move |key: Key| matches!(key, Key::Rowid(r) if r < rowid);
+        let after = |rowid| move |key: Key| matches!(key, Key::Rowid(r) if r > rowid);
+
+        let mut cursor = BTreeCursor::first(&db, root_page_num).unwrap();
+        assert_eq!(rowids(&mut cursor, true), (1..=800).collect::<Vec<_>>());
+        let mut cursor = BTreeCursor::last(&db, root_page_num).unwrap();
+        assert_eq!(
+            rowids(&mut cursor, false),
+            (1..=800).rev().collect::<Vec<_>>()
+        );
+
+        // every entry, on either side of each leaf and interior boundary
+        for rowid in 1..=800 {
+            let mut cursor = BTreeCursor::seek_ge(&db, root_page_num, before(rowid)).unwrap();
+            assert_eq!(cursor.key(), Some(Key::Rowid(rowid)));
+            cursor.prev().unwrap();
+            assert_eq!(cursor.key(), (rowid > 1).then_some(Key::Rowid(rowid - 1)));
+
+            let mut cursor = BTreeCursor::seek_le(&db, root_page_num, after(rowid)).unwrap();
+            assert_eq!(cursor.key(), Some(Key::Rowid(rowid)));
+            cursor.next().unwrap();
+            assert_eq!(cursor.key(), (rowid < 800).then_some(Key::Rowid(rowid + 1)));
+        }
+        let cursor = BTreeCursor::seek_ge(&db, root_page_num, before(801)).unwrap();
+        assert_eq!(cursor.key(), None);
+        let cursor = BTreeCursor::seek_le(&db, root_page_num, after(0)).unwrap();
+        assert_eq!(cursor.key(), None);
+        assert_eq!(BTreeCursor::count(&db, root_page_num).unwrap(), 800);
+    }
+
+    #[test]
+    fn walks_indexes_of_several_levels() {
+        // the keys of idx_deep_pad sit on four levels, the interior ones between
+        // the entries of their children
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        let root_page_num = db.idx_infos["idx_deep_pad"].root_page_num;
+        let pad = |rowid: i64| Column::Str(format!("{rowid:04}{:056}", 0));
+        let rowid = |cursor: &BTreeCursor| {
+            let (_, record) = cursor.entry()?;
+            let rowid = record[1].as_i64().unwrap();
+            assert_eq!(record[0], pad(rowid));
+            Some(rowid)
+        };
+        let before = |rowid| {
+            move |key: Key| match key {
+                Key::Record(record) => record[0].compare(&pad(rowid)).is_lt(),
+                Key::Rowid(_) => unreachable!(),
+            }
+        };
+        let after = |rowid| {
+            move |key: Key| match key {
+                Key::Record(record) => record[0].compare(&pad(rowid)).is_gt(),
+                Key::Rowid(_) => unreachable!(),
+            }
+        };
+
+        let mut cursor = BTreeCursor::first(&db, root_page_num).unwrap();
+        let mut rowids = vec![];
+        while let Some(r) = rowid(&cursor) {
+            rowids.push(r);
+            cursor.next().unwrap();
+        }
+        assert_eq!(rowids, (1..=800).collect::<Vec<_>>());
+        let mut cursor = BTreeCursor::last(&db, root_page_num).unwrap();
+        let mut rowids = vec![];
+        while let Some(r) = rowid(&cursor) {
+            rowids.push(r);
+            cursor.prev().unwrap();
+        }
+        assert_eq!(rowids, (1..=800).rev().collect::<Vec<_>>());
+
+        for r in 1..=800 {
+            let mut cursor = BTreeCursor::seek_ge(&db, root_page_num, before(r)).unwrap();
+            assert_eq!(rowid(&cursor), Some(r));
+            cursor.prev().unwrap();
+            assert_eq!(rowid(&cursor), (r > 1).then_some(r - 1));
+
+            let mut cursor = BTreeCursor::seek_le(&db, root_page_num, after(r)).unwrap();
+            assert_eq!(rowid(&cursor), Some(r));
+            cursor.next().unwrap();
+            assert_eq!(rowid(&cursor), (r < 800).then_some(r + 1));
+        }
+        assert_eq!(BTreeCursor::count(&db, root_page_num).unwrap(), 800);
+    }
+
+    #[test]
+    fn walks_empty_b_trees() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        for root_page_num in [
+            db.table_infos["blank"].root_page_num,
+            db.idx_infos["idx_blank_x"].root_page_num,
+        ] {
+            let mut cursor = BTreeCursor::first(&db, root_page_num).unwrap();
+            assert_eq!(cursor.key(), None);
+            cursor.next().unwrap();
+            assert_eq!(cursor.key(), None);
+            let mut cursor = BTreeCursor::last(&db, root_page_num).unwrap();
+            assert_eq!(cursor.key(), None);
+            cursor.prev().unwrap();
+            assert_eq!(cursor.key(), None);
+            let cursor = BTreeCursor::seek_ge(&db, root_page_num, |_| true).unwrap();
+            assert_eq!(cursor.key(), None);
+            let cursor = BTreeCursor::seek_le(&db, root_page_num, |_| true).unwrap();
+            assert_eq!(cursor.key(), None);
+            assert_eq!(BTreeCursor::count(&db, root_page_num).unwrap(), 0);
+        }
+    }
+}
@@ -1,24 +1,47 @@
-use core::panic;
-use std::{collections::BTreeMap, fs::File, os::unix::fs::FileExt};
+use std::{
+    borrow::Borrow,
+    cell::RefCell,
+    cmp::Ordering,
+    collections::BTreeMap,
+    fs::File,
+    ops::{Bound, RangeInclusive},
+    os::unix::fs::FileExt,
+    rc::Rc,
+};
 
 use crate::{
+    aggregate::Accumulator,
+    ast::{BinaryOp, Expr, IndexedColumn, JoinKind, Literal, NullsOrder, OrderingTerm},
+    cursor::{BTreeCursor, Key},
+    eval,
+    header::DbHeader,
     page::{
         Column, FirstPage, IdxInfo, IdxInteriorCell, IdxLeafCell, InteriorIdxPage,
         InteriorIdxRecordBody, InteriorTablePage, LeafIdxPage, LeafIdxRecordBody, LeafTableCell,
         LeafTablePage, Page, PageHeader, PageType, RecordBody, RecordHeader, TableInfo,
-        TableInteriorCell,
+        TableInteriorCell, TriggerInfo, ViewInfo, ROWID_NAMES,
     },
-    query::{CreateQuery, SelectQuery},
+    query::{Affinity, Collation, CreateQuery, SelectQuery},
     util::read_varint,
+    value::Value,
 };
 use anyhow::{anyhow, Result};
 
+/// Result rows produced one at a time.
+type Rows<'a> = Box<dyn Iterator<Item = Result<Vec<Column>>> + 'a>;
+
 pub struct Db {
     file: File,
     pub header: DbHeader,
+    // schema objects are keyed by their names in lowercase, names are compared
+    // without regard to case
     pub table_infos: BTreeMap<String, TableInfo>, // TableName->TableInfo
-    pub idx_infos: BTreeMap<String, IdxInfo>,     // TableName->TableInfo
-    pub num_cells: usize,                         // number of cells in the first page for now
+    pub idx_infos: BTreeMap<String, IdxInfo>,     // IdxName->IdxInfo
+    pub view_infos: BTreeMap<String, ViewInfo>,   // ViewName->ViewInfo
+    #[allow(dead_code)]
+    pub trigger_infos: BTreeMap<String, TriggerInfo>, // TriggerName->TriggerInfo
+    pub num_cells: usize,                         // number of objects in sqlite_schema
+    page_cache: RefCell<PageCache>,
 }
 
 impl Db {
@@ -26,36 +49,67 @@ impl Db {
         let mut db_header_bytes = [0; 100];
         file.read_exact_at(&mut db_header_bytes, 0)
             .map_err(|e| anyhow!("can't read 100 db header bytes from file: {e}"))?;
-        let page_size = u16::from_be_bytes([db_header_bytes[16], db_header_bytes[17]]);
-        //let text_encoding = u32::from_be_bytes([
-        //    db_header_bytes[56],
-        //    db_header_bytes[57],
-        //    db_header_bytes[58],
-        //    db_header_bytes[59],
-        //]);
-        let header = DbHeader { page_size };
-        let first_page = Self::get_first_page(&file)?;
+        let header = DbHeader::from_bytes(&db_header_bytes)
+            .map_err(|e| anyhow!("invalid db header: {e}"))?;
+        let file_size = file
+            .metadata()
+            .map_err(|e| anyhow!("can't get size of db file: {e}"))?
+            .len();
+        header
+            .check_file_size(file_size)
+            .map_err(|e| anyhow!("invalid db header: {e}"))?;
+        let first_page = Self::get_first_page(&file, &header)?;
         Ok(Db {
             file,
             header,
             table_infos: first_page.table_infos,
             idx_infos: first_page.idx_infos,
-            num_cells: first_page.page.cells.len(),
+            view_infos: first_page.view_infos,
+            trigger_infos: first_page.trigger_infos,
+            num_cells: first_page.cells.len(),
+            page_cache: RefCell::default(),
         })
     }
 
-    fn get_first_page(file: &File) -> Result<FirstPage> {
-        let page = match Self::_get_page(file, 0, Some(100)) {
-            Ok(p) => match p {
-                Page::LeafTable(leaf) => leaf,
-                _ => todo!("first page is not a leaf table page"),
-            },
-            Err(e) => return Err(anyhow!("error reading first page from file: {e}")),
-        };
+    /// Reads sqlite_schema, the table b-tree rooted at page 1.
+    fn get_first_page(file: &File, header: &DbHeader) -> Result<FirstPage> {
+        let page = Self::_get_page(file, header, 0, Some(100))
+            .map_err(|e| anyhow!("error reading first page from file: {e}"))?;
+
+        let mut cells = Vec::new();
+        Self::collect_schema_cells(file, header, page, &mut cells)?;
 
         let mut table_infos = BTreeMap::new();
         let mut idx_infos = BTreeMap::new();
-        for cell in &page.cells {
+        let mut view_infos = BTreeMap::new();
+        let mut trigger_infos = BTreeMap::new();
+        // TableName->columns of its automatic indexes
+        let mut autoindex_columns = BTreeMap::new();
+        // TableName->collating sequences of its columns declared with COLLATE
+        let mut column_collations = BTreeMap::new();
+        // (IdxName, TableName, root page) of automatic indexes
+        let mut autoindexes = Vec::new();
+        for cell in &cells {
+            let type_col = cell
+                .record_body
+                .columns
+                .first()
+                .ok_or(anyhow!("can't get type from cell 0"))?;
+            let object_type = match type_col {
+                Column::Str(s) => s.as_str(),
+                _ => return Err(anyhow!("wrong format of type column")),
+            };
+
+            let name_col = cell
+                .record_body
+                .columns
+                .get(1)
+                .ok_or(anyhow!("can't get name from cell 1"))?;
+            let name = match name_col {
+                Column::Str(s) => s.to_string(),
+                _ => return Err(anyhow!("wrong format of name column")),
+            };
+
             let page_name_col = cell
                 .record_body
                 .columns
@@ -71,11 +125,10 @@ impl Db {
                 .columns
                 .get(3)
                 .ok_or(anyhow!("can't get root page num from cell 3"))?;
-            let root_page_num = match root_page_number_col {
-                Column::I8(i) => *i as u32,
-                Column::I24(i) => (*i).try_into().unwrap(),
-                _ => return Err(anyhow!("wrong format of root page column")),
-            };
+            let root_page_num = root_page_number_col
+                .as_i64()
+                .and_then(|i| u32::try_from(i).ok())
+                .ok_or(anyhow!("wrong format of root page column"))?;
 
             let sql_col = cell
                 .record_body
@@ -84,43 +137,178 @@ impl Db {
                 .ok_or(anyhow!("can't get sql for table {table_name} from cell 4"))?;
             let sql = match sql_col {
                 Column::Str(s) => s,
+                // indexes created for UNIQUE and PRIMARY KEY constraints have no sql
+                Column::Null if object_type == "index" => {
+                    autoindexes.push((name, table_name, root_page_num));
+                    continue;
+                }
                 _ => return Err(anyhow!("wrong format of sql column")),
             };
 
-            match CreateQuery::from_sql(sql)? {
+            let key = name.to_ascii_lowercase();
+            match CreateQuery::from_sql(object_type, sql)? {
                 CreateQuery::CreateIdx(query) => {
                     let idx_info = IdxInfo {
                         root_page_num,
                         idx_name: query.idx_name,
+                        table_name: table_name.to_ascii_lowercase(),
                         columns: query.columns,
+                        where_clause: query.where_clause,
                     };
-                    idx_infos.insert(table_name, idx_info);
+                    idx_infos.insert(key, idx_info);
                 }
                 CreateQuery::CreateTable(query) => {
                     let table_info = TableInfo {
+                        table_name: name,
                         root_page_num,
                         column_orders: query.column_orders,
+                        column_affinities: query.column_affinities,
+                        without_rowid: query.without_rowid,
+                        primary_key: query.primary_key,
+                        rowid_alias: query.rowid_alias,
                     };
-                    table_infos.insert(table_name, table_info);
+                    autoindex_columns.insert(key.clone(), query.autoindex_columns);
+                    column_collations.insert(key.clone(), query.column_collations);
+                    table_infos.insert(key, table_info);
+                }
+                CreateQuery::CreateView(query) => {
+                    let view_info = ViewInfo {
+                        view_name: name,
+                        columns: query.columns,
+                        select_sql: query.select_sql,
+                    };
+                    view_infos.insert(key, view_info);
+                }
+                CreateQuery::CreateTrigger => {
+                    let trigger_info = TriggerInfo {
+                        trigger_name: name,
+                        table_name: table_name.to_ascii_lowercase(),
+                        sql: sql.to_string(),
+                    };
+                    trigger_infos.insert(key, trigger_info);
+                }
+            }
+        }
+
+        // indexed columns without COLLATE use the collating sequence of the column
+        for idx_info in idx_infos.values_mut() {
+            let Some(collations) = column_collations.get(&idx_info.table_name) else {
+                continue;
+            };
+            for column in &mut idx_info.columns {
+                if column.collation.is_none() {
+                    column.collation = collations.get(&column.name).cloned();
                 }
             }
         }
+
+        for (idx_name, table_name, root_page_num) in autoindexes {
+            // sqlite_autoindex_<table>_<n> backs the n-th constraint of the table, an
+            // index that matches none is left out and the table scanned instead
+            let n = idx_name
+                .strip_prefix(&format!("sqlite_autoindex_{table_name}_"))
+                .and_then(|n| n.parse::<usize>().ok());
+            let table_name = table_name.to_ascii_lowercase();
+            let columns = autoindex_columns
+                .get(&table_name)
+                .zip(n)
+                .and_then(|(c, n)| c.get(n.checked_sub(1)?));
+            let Some(columns) = columns.cloned() else {
+                continue;
+            };
+            let idx_info = IdxInfo {
+                root_page_num,
+                idx_name: idx_name.clone(),
+                table_name,
+                columns,
+                where_clause: None,
+            };
+            idx_infos.insert(idx_name.to_ascii_lowercase(), idx_info);
+        }
+
         Ok(FirstPage {
-            page,
+            cells,
             table_infos,
             idx_infos,
+            view_infos,
+            trigger_infos,
         })
     }
 
+    /// Collects the leaf cells of the schema table, descending into interior pages
+    /// when the schema doesn't fit on page 1.
+    fn collect_schema_cells(
+        file: &File,
+        header: &DbHeader,
+        page: Page,
+        cells: &mut Vec<LeafTableCell>,
+    ) -> Result<()> {
+        match page {
+            Page::LeafTable(mut leaf) => cells.append(&mut leaf.cells),
+            Page::InteriorTable(interior) => {
+                let children = interior
+                    .cells
+                    .iter()
+                    .map(|c| c.left_child_page_num)
+                    .chain(interior.page_header.rightmost_pointer);
+                for child_page_num in children {
+                    let child_offset = (child_page_num - 1) as u64 * header.page_size as u64;
+                    let child = Self::_get_page(file, header, child_offset, None).map_err(|e| {
+                        anyhow!("error reading schema page {child_page_num} from file: {e}")
+                    })?;
+                    Self::collect_schema_cells(file, header, child, cells)?;
+                }
+            }
+            _ => return Err(anyhow!("sqlite_schema page is not a table page")),
+        }
+
+        Ok(())
+    }
+
+    /// Returns the bytes reserved for extensions at the end of page `page_num`.
+    #[allow(dead_code)]
+    pub fn get_reserved_bytes(&self, page_num: u32) -> Result<Vec<u8>> {
+        if page_num == 0 {
+            return Err(anyhow!("page numbers start at 1"));
+        }
+        let page_offset = (page_num - 1) as u64 * self.header.page_size as u64;
+        let mut reserved = vec![0_u8; self.header.reserved_bytes as usize];
+        self.file
+            .read_exact_at(&mut reserved, page_offset + self.header.usable_size())
+            .map_err(|e| anyhow!("can't read reserved bytes of page {page_num}: {e}"))?;
+        Ok(reserved)
+    }
+
+    /// Returns a page of a b-tree by number, from the cache if it was used recently.
+    pub fn page(&self, page_num: u32) -> Result<Rc<Page>> {
+        if let Some(page) = self.page_cache.borrow_mut().get(page_num) {
+            return Ok(page);
+        }
+        let page_offset = (page_num - 1) as u64 * self.header.page_size as u64;
+        let page = Rc::new(self.get_page(page_offset, None)?);
+        self.page_cache.borrow_mut().insert(page_num, page.clone());
+        Ok(page)
+    }
+
     pub fn get_page(&self, page_offset: u64, page_header_offset: Option<u64>) -> Result<Page> {
-        Self::_get_page(&self.file, page_offset, page_header_offset)
+        Self::_get_page(&self.file, &self.header, page_offset, page_header_offset)
+    }
+
+    /// Reads only the header of a page.
+    pub fn page_header(&self, page_num: u32) -> Result<PageHeader> {
+        let page_offset = (page_num - 1) as u64 * self.header.page_size as u64;
+        Self::get_page_header(&self.file, page_offset)
+            .map_err(|e| anyhow!("can't read header of page {page_num}: {e}"))
     }
 
     pub fn _get_page(
         file: &File,
+        header: &DbHeader,
         page_offset: u64,
         page_header_offset: Option<u64>,
     ) -> Result<Page> {
+        let file = &PageBuf::read(file, page_offset, header.page_size as usize)
+            .map_err(|e| anyhow!("can't read page at offset {page_offset}: {e}"))?;
         let page_header_offset = page_header_offset.unwrap_or(0);
         let page_header = Self::get_page_header(file, page_offset + page_header_offset)
             .map_err(|e| anyhow!("can't read page header from file at page offset {page_offset}, page header offset {page_header_offset}: {e}"))?;
@@ -134,12 +322,22 @@ impl Db {
 
         let mut cell_offset = page_data_offset;
 
+        // cells live between the end of the cell pointer array and the reserved space
+        let cell_content_start = page_data_offset - page_offset + 2 * page_header.num_cells as u64;
+        let usable_size = header.usable_size();
+
         let mut cell_pointer_array = Vec::with_capacity(page_header.num_cells.into());
         for i in 0..page_header.num_cells {
             let mut buf = [0_u8; 2];
             file.read_exact_at(&mut buf, cell_offset)
                 .map_err(|e| anyhow!("can't read cell {i} at offset {cell_offset}: {e}"))?;
-            cell_pointer_array.push(u16::from_be_bytes(buf));
+            let pointer = u16::from_be_bytes(buf);
+            if (pointer as u64) < cell_content_start || pointer as u64 >= usable_size {
+                return Err(anyhow!(
+                    "cell {i} pointer {pointer} of page at offset {page_offset} is outside of cell content area {cell_content_start}..{usable_size}"
+                ));
+            }
+            cell_pointer_array.push(pointer);
             cell_offset += 2;
         }
 
@@ -149,6 +347,7 @@ impl Db {
                     cell_pointer_array,
                     &page_header,
                     file,
+                    header,
                     page_offset,
                 )?;
 
@@ -159,6 +358,7 @@ impl Db {
                     cell_pointer_array,
                     &page_header,
                     file,
+                    header,
                     page_offset,
                 )?;
                 Ok(Page::InteriorIdx(InteriorIdxPage { page_header, cells }))
@@ -176,9 +376,14 @@ impl Db {
                 }))
             }
             PageType::LeafIndex => {
-                let cells =
-                    Self::get_leaf_idx_cells(cell_pointer_array, &page_header, file, page_offset)
-                        .map_err(|e| anyhow!("can't get leaf idx cells: {e}"))?;
+                let cells = Self::get_leaf_idx_cells(
+                    cell_pointer_array,
+                    &page_header,
+                    file,
+                    header,
+                    page_offset,
+                )
+                .map_err(|e| anyhow!("can't get leaf idx cells: {e}"))?;
                 Ok(Page::LeafIndex(LeafIdxPage { page_header, cells }))
             }
         }
@@ -187,30 +392,38 @@ impl Db {
     fn get_leaf_idx_cells(
         cell_pointer_array: Vec<u16>,
         page_header: &PageHeader,
-        file: &File,
+        file: &impl FileExt,
+        header: &DbHeader,
         page_offset: u64,
     ) -> Result<Vec<IdxLeafCell>> {
         let mut cells = Vec::with_capacity(page_header.num_cells.into());
-        let mut buf_varint = [0_u8; 9];
         for pointer in &cell_pointer_array {
             let mut pointer = *pointer as u64;
             pointer += page_offset;
             let mut current_offset = 0_u64;
 
-            // payload size, skipping
-            file.read_exact_at(&mut buf_varint, pointer + current_offset)
+            // payload size
+            let (payload_size, o) = Self::read_varint_at(file, pointer + current_offset)
                 .map_err(|e| anyhow!("can't read number of bytes of payload of leaf idx cell: {e} at pointer {pointer}"))?;
-            let (_payload_size, o) = read_varint(&buf_varint);
             current_offset += o as u64;
 
+            let payload = Self::read_payload(
+                file,
+                header,
+                page_offset,
+                pointer + current_offset,
+                payload_size as u64,
+                header.max_local_idx(),
+            )
+            .map_err(|e| anyhow!("can't read payload of leaf idx cell: {e}"))?;
+
             // record header
-            let (record_header, o) = RecordHeader::from_file(file, pointer + current_offset)
+            let (record_header, o) = RecordHeader::from_bytes(&payload)
                 .map_err(|e| anyhow!("can't read record header of leaf idx page: {e}"))?;
-            current_offset += o;
 
             // columns
             let (columns, _) = record_header
-                .read_columns(file, pointer + current_offset)
+                .read_columns(&payload[o..], header.text_encoding)
                 .map_err(|e| anyhow!("can't read columns of leaf idx page {e} "))?;
 
             cells.push(IdxLeafCell {
@@ -225,7 +438,8 @@ impl Db {
     fn get_interior_idx_cells(
         cell_pointer_array: Vec<u16>,
         page_header: &PageHeader,
-        file: &File,
+        file: &impl FileExt,
+        header: &DbHeader,
         page_offset: u64,
     ) -> Result<Vec<IdxInteriorCell>> {
         let mut cells = Vec::with_capacity(page_header.num_cells.into());
@@ -233,7 +447,6 @@ impl Db {
             let mut pointer = *pointer as u64;
             pointer += page_offset;
             let mut buf_u32 = [0_u8; 4];
-            let mut buf_varint = [0_u8; 9];
 
             // left child
             file.read_exact_at(&mut buf_u32, pointer)
@@ -241,21 +454,28 @@ impl Db {
             let left_child_page_num = u32::from_be_bytes(buf_u32);
             let mut current_offset = 4;
 
-            // payload size, skipping
-            file.read_exact_at(&mut buf_varint, pointer + current_offset)
+            // payload size
+            let (payload_size, o) = Self::read_varint_at(file, pointer + current_offset)
                 .map_err(|e| anyhow!("can't read number of bytes of payload of interior idx cell: {e} at pointer {pointer}"))?;
-            let (_payload_size, o) = read_varint(&buf_varint);
             current_offset += o as u64;
 
+            let payload = Self::read_payload(
+                file,
+                header,
+                page_offset,
+                pointer + current_offset,
+                payload_size as u64,
+                header.max_local_idx(),
+            )
+            .map_err(|e| anyhow!("can't read payload of interior idx cell: {e}"))?;
+
             // record header
-            let (record_header, o) = RecordHeader::from_file(file, pointer + current_offset)?;
-            current_offset += o;
+            let (record_header, o) = RecordHeader::from_bytes(&payload)?;
 
-            let (columns, o) = record_header.read_columns(file, pointer + current_offset)?;
-            current_offset += o;
+            let (columns, _) = record_header.read_columns(&payload[o..], header.text_encoding)?;
 
-            file.read_exact_at(&mut buf_varint, pointer + current_offset)?;
-            let (rowid, _) = read_varint(&buf_varint);
+            // the rowid of the indexed row is the last column of the key
+            let rowid = columns.last().and_then(Column::as_i64);
 
             cells.push(IdxInteriorCell {
                 left_child_page_num,
@@ -270,7 +490,7 @@ impl Db {
     fn get_interior_table_cells(
         cell_pointer_array: Vec<u16>,
         page_header: &PageHeader,
-        file: &File,
+        file: &impl FileExt,
         page_offset: u64,
     ) -> Result<Vec<TableInteriorCell>> {
         let mut cells = Vec::with_capacity(page_header.num_cells.into());
@@ -278,16 +498,14 @@ impl Db {
             let mut pointer = *pointer as u64;
             pointer += page_offset;
             let mut buf_u32 = [0_u8; 4]; // for integers
-            let mut buf_varint = [0_u8; 9]; // for varints
 
             file.read_exact_at(&mut buf_u32, pointer)
                 .map_err(|e| anyhow!("can't read cell size: {e} at pointer {pointer}"))?;
             let left_child_page_num = u32::from_be_bytes(buf_u32);
 
             // rowid:
-            file.read_exact_at(&mut buf_varint, pointer + 4)
+            let (rowid, _) = Self::read_varint_at(file, pointer + 4)
                 .map_err(|e| anyhow!("can't read cell rowid: {e} at pointer {pointer}"))?;
-            let (rowid, _) = read_varint(&buf_varint);
 
             cells.push(TableInteriorCell {
                 left_child_page_num,
@@ -301,32 +519,38 @@ impl Db {
     fn get_leaf_table_cells(
         cell_pointer_array: Vec<u16>,
         page_header: &PageHeader,
-        file: &File,
+        file: &impl FileExt,
+        header: &DbHeader,
         page_offset: u64,
     ) -> Result<Vec<LeafTableCell>> {
         let mut cells = Vec::with_capacity(page_header.num_cells.into());
         for pointer in &cell_pointer_array {
             let mut pointer = *pointer as u64;
             pointer += page_offset;
-            let mut buf = [0_u8; 9]; // for varints
-
             let mut current_offset = 0_u64;
             // size:
-            file.read_exact_at(&mut buf, pointer)
+            let (size, o) = Self::read_varint_at(file, pointer)
                 .map_err(|e| anyhow!("can't read cell size: {e} at pointer {pointer}"))?;
-            let (size, o) = read_varint(&buf);
             current_offset += o as u64;
 
             // rowid:
-            file.read_exact_at(&mut buf, pointer + current_offset)
+            let (rowid, o) = Self::read_varint_at(file, pointer + current_offset)
                 .map_err(|e| anyhow!("can't read cell rowid: {e} at pointer {pointer}"))?;
-            let (rowid, o) = read_varint(&buf);
             current_offset += o as u64;
 
-            let (record_header, o) = RecordHeader::from_file(file, pointer + current_offset)?;
-            current_offset += o;
+            let payload = Self::read_payload(
+                file,
+                header,
+                page_offset,
+                pointer + current_offset,
+                size as u64,
+                header.max_local_table_leaf(),
+            )
+            .map_err(|e| anyhow!("can't read payload of row {rowid}: {e}"))?;
 
-            let (columns, _) = record_header.read_columns(file, pointer + current_offset)?;
+            let (record_header, o) = RecordHeader::from_bytes(&payload)?;
+
+            let (columns, _) = record_header.read_columns(&payload[o..], header.text_encoding)?;
 
             let cell = LeafTableCell {
                 size,
@@ -341,7 +565,83 @@ impl Db {
         Ok(cells)
     }
 
-    fn get_page_header(file: &File, offset: u64) -> Result<PageHeader> {
+    /// Reads a cell payload of `payload_size` bytes whose local part starts at `pointer`.
+    /// If the payload doesn't fit into `max_local` bytes, the rest of it is collected
+    /// from the chain of overflow pages.
+    fn read_payload(
+        file: &impl FileExt,
+        header: &DbHeader,
+        page_offset: u64,
+        pointer: u64,
+        payload_size: u64,
+        max_local: u64,
+    ) -> Result<Vec<u8>> {
+        let usable_size = header.usable_size();
+        let local_size = if payload_size <= max_local {
+            payload_size
+        } else {
+            let min_local = header.min_local();
+            let k = min_local + (payload_size - min_local) % (usable_size - 4);
+            if k <= max_local {
+                k
+            } else {
+                min_local
+            }
+        };
+
+        // the local part and the overflow page number must not run into the reserved space
+        let local_end = pointer + local_size + if local_size < payload_size { 4 } else { 0 };
+        if local_end > page_offset + usable_size {
+            return Err(anyhow!(
+                "local payload of {local_size} bytes at {pointer} runs past the usable part of the page"
+            ));
+        }
+
+        let mut payload = vec![0_u8; payload_size as usize];
+        file.read_exact_at(&mut payload[..local_size as usize], pointer)
+            .map_err(|e| anyhow!("can't read {local_size} local payload bytes: {e}"))?;
+        if local_size == payload_size {
+            return Ok(payload);
+        }
+
+        let mut buf_u32 = [0_u8; 4];
+        file.read_exact_at(&mut buf_u32, pointer + local_size)
+            .map_err(|e| anyhow!("can't read first overflow page number: {e}"))?;
+        let mut overflow_page_num = u32::from_be_bytes(buf_u32);
+
+        // every overflow page starts with the number of the next one followed by content
+        let mut read = local_size as usize;
+        while read < payload.len() {
+            if overflow_page_num == 0 {
+                return Err(anyhow!(
+                    "overflow chain ended after {read} of {payload_size} payload bytes"
+                ));
+            }
+            let page_offset = (overflow_page_num - 1) as u64 * header.page_size as u64;
+            file.read_exact_at(&mut buf_u32, page_offset)
+                .map_err(|e| anyhow!("can't read overflow page {overflow_page_num}: {e}"))?;
+            let chunk = (payload.len() - read).min(usable_size as usize - 4);
+            file.read_exact_at(&mut payload[read..read + chunk], page_offset + 4)
+                .map_err(|e| anyhow!("can't read overflow page {overflow_page_num}: {e}"))?;
+            read += chunk;
+            overflow_page_num = u32::from_be_bytes(buf_u32);
+        }
+
+        Ok(payload)
+    }
+
+    /// Reads a varint at `offset`. Unlike reading a fixed 9-byte buffer this also
+    /// works for varints that end right at the end of the file.
+    fn read_varint_at(file: &impl FileExt, offset: u64) -> std::io::Result<(i64, u8)> {
+        let mut buf = [0_u8; 9];
+        let n = file.read_at(&mut buf, offset)?;
+        if n == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(read_varint(&buf[..n]))
+    }
+
+    fn get_page_header(file: &impl FileExt, offset: u64) -> Result<PageHeader> {
         let mut page_header = [0; 12];
         file.read_exact_at(&mut page_header, offset)
             .map_err(|e| anyhow!("can't read 8 bytes of page header from file: {e}"))?;
@@ -374,403 +674,2346 @@ impl Db {
         })
     }
 
-    pub fn execute_select(&self, query: SelectQuery) -> Result<Vec<Vec<String>>> {
-        let idx_info = self.idx_infos.get(&query.table_name);
-        if idx_info.is_some() {
-            let table_name = &query.table_name;
+    /// Returns the indexes on the table `table_name` that have an entry for each of
+    /// its rows, which leaves out partial indexes.
+    pub fn table_idx_infos(&self, table_name: &str) -> Vec<&IdxInfo> {
+        self.idx_infos
+            .values()
+            .filter(|i| i.table_name == table_name && i.where_clause.is_none())
+            .collect()
+    }
 
-            let table_info = self.table_infos.get(table_name).unwrap();
+    /// Decides how to find the rows of the queried table.
+    fn plan_select<'a>(&'a self, query: &SelectQuery, table_info: &TableInfo) -> ScanPlan<'a> {
+        // secondary indexes of WITHOUT ROWID tables point to primary keys, not rowids
+        if table_info.without_rowid {
+            return ScanPlan::TableScan;
+        }
 
-            let rowids = self
-                .query_idx(table_name, &query.where_value.clone().unwrap())?
-                .unwrap();
+        // an index can only be searched by a prefix of its columns, take the one
+        // whose prefix covers the most conditions
+        let best = self
+            .table_idx_infos(&query.table_name)
+            .into_iter()
+            .filter_map(|idx_info| {
+                let (fixed, range) = Self::seek_range(&idx_info.columns, query, table_info)?;
+                Some((idx_info, fixed, range))
+            })
+            .max_by_key(|(_, fixed, range)| (*fixed, range.start != Bound::Unbounded));
 
-            let root_offset = (table_info.root_page_num - 1) as u64 * self.header.page_size as u64;
-            let root_page = self.get_page(root_offset, None)?;
+        // a single rowid beats any index, a range of them an index range
+        let plan = |(idx_info, fixed, range)| ScanPlan::IdxSeek {
+            idx_info,
+            fixed,
+            range,
+        };
+        match (Self::rowid_range(query, table_info), best) {
+            (Some(rowids), _) if rowids.start() == rowids.end() => ScanPlan::RowidRange(rowids),
+            (_, Some(best)) if best.1 > 0 => plan(best),
+            (Some(rowids), _) => ScanPlan::RowidRange(rowids),
+            (None, Some(best)) => plan(best),
+            (None, None) => ScanPlan::TableScan,
+        }
+    }
 
-            let mut res: Vec<Vec<String>> = Vec::new();
-            for rowid in &rowids {
-                let r = self.get_row(&root_page, *rowid, table_info, &query)?;
-                if !r.is_empty() {
-                    res.push(r);
+    /// Returns the keys of an index on `columns` the WHERE clause limits the rows to:
+    /// the number of leading columns it fixes to one value, and the range the values
+    /// and the bounds on the next column allow.
+    fn seek_range(
+        columns: &[IndexedColumn],
+        query: &SelectQuery,
+        table_info: &TableInfo,
+    ) -> Option<(usize, KeyRange)> {
+        // descending columns would need the search to go the other way, and the
+        // values are compared as BINARY, which other collating sequences order apart
+        let mut ascending = columns
+            .iter()
+            .take_while(|c| !c.descending && compares_binary(c));
+        let values = Self::seek_values(
+            ascending.clone().map(|c| c.name.as_str()),
+            query,
+            table_info,
+        );
+        let fixed = values.len();
+        let bounds = ascending
+            .nth(fixed)
+            .and_then(|c| Self::column_bounds(&c.name, query, table_info));
+        let range = match bounds {
+            None if fixed == 0 => return None,
+            None => KeyRange::eq(values),
+            Some((start, end)) => {
+                let key = |value| [values.clone(), vec![value]].concat();
+                KeyRange {
+                    // NULLs come first in an index and no comparison is true for them
+                    start: match start {
+                        Bound::Unbounded => Bound::Excluded(key(Column::Null)),
+                        start => start.map(key),
+                    },
+                    end: match end {
+                        Bound::Unbounded => Bound::Included(values.clone()),
+                        end => end.map(key),
+                    },
+                    orders: vec![],
+                }
+            }
+        };
+        Some((fixed, range))
+    }
+
+    /// Returns the bounds the WHERE clause puts on the values of a column by comparing
+    /// it with literals or matching it with a LIKE prefix, if it does.
+    fn column_bounds(
+        column_name: &str,
+        query: &SelectQuery,
+        table_info: &TableInfo,
+    ) -> Option<(Bound<Column>, Bound<Column>)> {
+        let affinity = table_info
+            .column_affinities
+            .get(column_name)
+            .copied()
+            .unwrap_or(Affinity::Blob);
+        let (mut start, mut end) = (Bound::Unbounded, Bound::Unbounded);
+        for term in query.where_clause.iter().flat_map(Expr::conjuncts) {
+            let Expr::Binary { op, lhs, rhs } = term else {
+                continue;
+            };
+            let (op, literal) = match (lhs.as_ref(), rhs.as_ref()) {
+                (Expr::Column { table: None, name }, Expr::Literal(literal))
+                    if name == column_name =>
+                {
+                    (*op, literal)
                 }
+                (Expr::Literal(literal), Expr::Column { table: None, name })
+                    if name == column_name && *op != BinaryOp::Like =>
+                {
+                    (op.swapped(), literal)
+                }
+                _ => continue,
+            };
+            if *literal == Literal::Null {
+                continue;
             }
 
-            return Ok(res);
+            let value = affinity.apply(eval::literal_value(literal));
+            match (op, literal) {
+                (BinaryOp::Gt, _) => tighten(&mut start, Bound::Excluded(value), Ordering::Greater),
+                (BinaryOp::Ge, _) => tighten(&mut start, Bound::Included(value), Ordering::Greater),
+                (BinaryOp::Lt, _) => tighten(&mut end, Bound::Excluded(value), Ordering::Less),
+                (BinaryOp::Le, _) => tighten(&mut end, Bound::Included(value), Ordering::Less),
+                // LIKE ignores the case of ASCII letters, so the matches of a prefix sort
+                // between the prefix in upper case and the prefix in lower case
+                (BinaryOp::Like, Literal::Text(pattern)) if affinity == Affinity::Text => {
+                    let mut prefix = pattern
+                        .chars()
+                        .take_while(|c| *c != '%' && *c != '_')
+                        .collect::<String>();
+                    let Some(last) = prefix.pop() else {
+                        continue;
+                    };
+                    let lowest = format!("{prefix}{last}").to_ascii_uppercase();
+                    tighten(
+                        &mut start,
+                        Bound::Included(Column::Str(lowest)),
+                        Ordering::Greater,
+                    );
+                    let last = last.to_ascii_lowercase();
+                    if let Some(next) = char::from_u32(last as u32 + 1) {
+                        let highest = format!("{}{next}", prefix.to_ascii_lowercase());
+                        tighten(
+                            &mut end,
+                            Bound::Excluded(Column::Str(highest)),
+                            Ordering::Less,
+                        );
+                    }
+                }
+                _ => {}
+            }
         }
 
-        let table_info = self
-            .table_infos
-            .get(&query.table_name)
-            .ok_or(anyhow!("no such table: {}", &query.table_name))?;
+        match (&start, &end) {
+            (Bound::Unbounded, Bound::Unbounded) => None,
+            _ => Some((start, end)),
+        }
+    }
 
-        let page = self.get_page(
-            (table_info.root_page_num - 1) as u64 * self.header.page_size as u64,
-            None,
-        )?;
+    /// Returns the rowids the WHERE clause limits the rows to by comparing the rowid
+    /// with literals, if it does.
+    fn rowid_range(query: &SelectQuery, table_info: &TableInfo) -> Option<RangeInclusive<i64>> {
+        let (mut start, mut end) = (i64::MIN, i64::MAX);
+        let mut limited = false;
+        for term in query.where_clause.iter().flat_map(Expr::conjuncts) {
+            let Expr::Binary { op, lhs, rhs } = term else {
+                continue;
+            };
+            let (op, literal) = match (lhs.as_ref(), rhs.as_ref()) {
+                (Expr::Column { table: None, name }, Expr::Literal(literal))
+                    if table_info.is_rowid(name) =>
+                {
+                    (*op, literal)
+                }
+                (Expr::Literal(literal), Expr::Column { table: None, name })
+                    if table_info.is_rowid(name) =>
+                {
+                    (op.swapped(), literal)
+                }
+                _ => continue,
+            };
+            let op = match op {
+                // the rowid is never NULL, so IS compares it like =
+                BinaryOp::Is => BinaryOp::Eq,
+                BinaryOp::Eq | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => op,
+                _ => continue,
+            };
 
-        match page {
-            Page::LeafTable(p) => Self::query_leaf_page(&p, &query, table_info),
-            Page::InteriorTable(p) => self.query_interior_page(&p, &query, table_info),
-            _ => todo!(),
+            // the rowid is an integer, other values compare as numbers if they can
+            let value = Affinity::Integer.apply(eval::literal_value(literal));
+            let (low, high) = match (op, value) {
+                // comparisons with NULL are never true
+                (_, Column::Null) => (i64::MAX, i64::MIN),
+                (op, Column::F64(r)) => match op {
+                    BinaryOp::Eq => (r.ceil() as i64, r.floor() as i64),
+                    BinaryOp::Gt => ((r.floor() as i64).saturating_add(1), i64::MAX),
+                    BinaryOp::Ge => (r.ceil() as i64, i64::MAX),
+                    BinaryOp::Lt => (i64::MIN, (r.ceil() as i64).saturating_sub(1)),
+                    _ => (i64::MIN, r.floor() as i64),
+                },
+                (op, value) => match (op, value.as_i64()) {
+                    (BinaryOp::Eq, Some(i)) => (i, i),
+                    (BinaryOp::Gt, Some(i)) => (i.saturating_add(1), i64::MAX),
+                    (BinaryOp::Ge, Some(i)) => (i, i64::MAX),
+                    (BinaryOp::Lt, Some(i)) => (i64::MIN, i.saturating_sub(1)),
+                    (_, Some(i)) => (i64::MIN, i),
+                    // text and blobs are greater than every number
+                    (BinaryOp::Lt | BinaryOp::Le, None) => continue,
+                    (_, None) => (i64::MAX, i64::MIN),
+                },
+            };
+            start = start.max(low);
+            end = end.min(high);
+            limited = true;
         }
+
+        limited.then_some(start..=end)
     }
 
-    fn query_interior_page(
-        &self,
-        interior_page: &InteriorTablePage,
+    /// Returns the values a prefix of `columns` must be equal to by the WHERE clause,
+    /// converted to the affinity of their column like the values in an index.
+    fn seek_values<'c>(
+        columns: impl IntoIterator<Item = &'c str>,
         query: &SelectQuery,
         table_info: &TableInfo,
-    ) -> Result<Vec<Vec<String>>> {
-        let mut res = Vec::new();
-        for cell in &interior_page.cells {
-            let pointer = (cell.left_child_page_num - 1) as u64 * self.header.page_size as u64;
-            let child = self.get_page(pointer, None)?;
-            match child {
-                Page::LeafTable(leaf) => {
-                    let mut r = Self::query_leaf_page(&leaf, query, table_info)?;
-                    res.append(&mut r);
-                }
-                Page::InteriorTable(interior_child) => {
-                    let mut r = self.query_interior_page(&interior_child, query, table_info)?;
-                    res.append(&mut r);
-                }
-                _ => {
-                    //dbg!("other type");
-                }
+    ) -> Vec<Column> {
+        columns
+            .into_iter()
+            .map_while(|c| {
+                let value = eval::literal_value(query.where_value(c)?);
+                Some(match table_info.column_affinities.get(c) {
+                    Some(affinity) => affinity.apply(value),
+                    None => value,
+                })
+            })
+            .collect()
+    }
+
+    /// Tells whether the rows found by `plan` already come in the ORDER BY order:
+    /// `Some(false)` if they do, `Some(true)` if in exactly the opposite order and
+    /// `None` if they have to be sorted.
+    fn plan_order(plan: &ScanPlan, query: &SelectQuery, table_info: &TableInfo) -> Option<bool> {
+        // columns the seek fixes to one value, and the (name, descending, binary)
+        // columns the rows are ordered by
+        let (fixed, mut natural) = match plan {
+            ScanPlan::IdxSeek {
+                idx_info, fixed, ..
+            } => {
+                let (fixed, rest) = idx_info.columns.split_at(*fixed);
+                (
+                    fixed.iter().map(|c| c.name.as_str()).collect(),
+                    rest.iter()
+                        .map(|c| (c.name.as_str(), c.descending, compares_binary(c)))
+                        .collect(),
+                )
             }
+            ScanPlan::RowidRange(_) => (vec![], vec![]),
+            ScanPlan::TableScan if !table_info.without_rowid => (vec![], vec![]),
+            // the rows are in primary key order, the seek fixes a prefix of it
+            ScanPlan::TableScan => {
+                let primary_key = &table_info.primary_key;
+                let orders = KeyRange::orders(primary_key);
+                let key = primary_key[..orders.len()].iter().map(|c| c.name.as_str());
+                let fixed = Self::seek_values(key, query, table_info).len();
+                let (fixed, rest) = primary_key.split_at(fixed);
+                (
+                    fixed.iter().map(|c| c.name.as_str()).collect(),
+                    rest.iter()
+                        .map(|c| (c.name.as_str(), c.descending, compares_binary(c)))
+                        .collect(),
+                )
+            }
+        };
+        // index entries with equal keys, and the rows of a table, follow the rowid
+        if !table_info.without_rowid {
+            natural.push((ROWID_NAMES[0], false, true));
         }
 
-        let rightmost = self.get_page(
-            (interior_page.page_header.rightmost_pointer.unwrap() - 1) as u64
-                * self.header.page_size as u64,
-            None,
-        )?;
-        match rightmost {
-            Page::LeafTable(leaf) => {
-                let mut r = Self::query_leaf_page(&leaf, query, table_info)?;
-                res.append(&mut r);
+        let mut natural = natural.into_iter();
+        let mut reverse = None;
+        for term in &query.order_by {
+            let Expr::Column { table: None, name } = &term.expr else {
+                return None;
+            };
+            if fixed.contains(&name.as_str()) {
+                continue;
             }
-            Page::InteriorTable(interior_child) => {
-                let mut r = self.query_interior_page(&interior_child, query, table_info)?;
-                res.append(&mut r);
+            let (column, descending, binary) = natural.next()?;
+            let is_rowid = table_info.is_rowid(name);
+            if column != name && !(column == ROWID_NAMES[0] && is_rowid) {
+                return None;
             }
-            _ => {
-                dbg!("other type");
+            // ORDER BY compares as BINARY, which other collating sequences order apart
+            if !binary {
+                return None;
+            }
+            // NULLs are the smallest values in an index, so only the default
+            // placement follows from scanning it either way
+            let nulls_first = term
+                .nulls
+                .map_or(!term.descending, |n| n == NullsOrder::First);
+            if nulls_first == term.descending && !is_rowid {
+                return None;
+            }
+            let term_reverse = term.descending != descending;
+            if *reverse.get_or_insert(term_reverse) != term_reverse {
+                return None;
             }
         }
 
-        Ok(res)
+        Some(reverse.unwrap_or(false))
     }
 
-    fn query_leaf_page(
-        leaf_page: &LeafTablePage,
-        query: &SelectQuery,
-        table_info: &TableInfo,
-    ) -> Result<Vec<Vec<String>>> {
-        let mut result = Vec::new();
+    /// Compares the sort keys of two rows by the ORDER BY terms.
+    fn cmp_sort_keys(order_by: &[OrderingTerm], a: &[Column], b: &[Column]) -> Ordering {
+        order_by
+            .iter()
+            .zip(a.iter().zip(b))
+            .map(|(term, (a, b))| {
+                let nulls_first = term
+                    .nulls
+                    .map_or(!term.descending, |n| n == NullsOrder::First);
+                match (a, b) {
+                    (Column::Null, Column::Null) => Ordering::Equal,
+                    (Column::Null, _) if nulls_first => Ordering::Less,
+                    (Column::Null, _) => Ordering::Greater,
+                    (_, Column::Null) if nulls_first => Ordering::Greater,
+                    (_, Column::Null) => Ordering::Less,
+                    (a, b) if term.descending => a.compare(b).reverse(),
+                    (a, b) => a.compare(b),
+                }
+            })
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
 
-        for cell in &leaf_page.cells {
-            let mut row = vec![String::from(""); query.columns.len()];
+    /// Returns the result rows of a query as they are found.
+    pub fn execute_select(
+        &self,
+        query: SelectQuery,
+    ) -> Result<impl Iterator<Item = Result<Vec<Value>>> + '_> {
+        let rows = self.select_rows(query)?;
+        Ok(rows.map(|row| row.map(|row| row.into_iter().map(Value::from).collect())))
+    }
 
-            let mut write_row = true;
-            if query.where_column.is_some() {
-                write_row = false;
+    /// Returns the result rows of a query.
+    fn select_rows(&self, mut query: SelectQuery) -> Result<Rows<'_>> {
+        if let Some(view_info) = self.view_infos.get(&query.table_name) {
+            if query.joins.is_empty() {
+                return self.select_view_rows(view_info, query);
             }
+        }
+        self.resolve_aliases(&mut query)?;
 
-            for column_name in table_info.column_orders.keys() {
-                let order = table_info.column_orders[column_name];
-                let column = &cell.record_body.columns[order];
+        // a column that doesn't exist is an error even if no row is read
+        let from = FromTables::new(self, &query)?;
+        for expr in query
+            .columns
+            .iter()
+            .chain(query.joins.iter().flat_map(|join| &join.on))
+            .chain(&query.where_clause)
+            .chain(&query.group_by)
+            .chain(&query.having)
+            .chain(query.order_by.iter().map(|term| &term.expr))
+        {
+            from.tables_used(expr)?;
+        }
 
-                let column_value = match column {
-                    Column::Str(s) => s.to_string(),
-                    Column::I8(i) => i.to_string(),
-                    Column::I16(i) => i.to_string(),
-                    Column::I24(i) => i.to_string(),
-                    Column::Zero => String::from("0"),
-                    Column::One => String::from("1"),
-                    Column::Null => cell.rowid.to_string(),
-                };
-                if query.where_column == Some(column_name.to_string())
-                    && query.where_value == Some(column_value.to_string())
-                {
-                    write_row = true;
-                }
+        let (num_columns, offset, limit) = (query.columns.len(), query.offset, query.limit);
+        let rows = match query.is_aggregate() {
+            true => Box::new(self.select_groups(&query)?.into_iter().map(Ok)),
+            false => self.source_rows(query)?,
+        };
 
-                if query.columns.contains_key(column_name) {
-                    row[*query.columns.get(column_name).unwrap()] = column_value;
-                }
-            }
-            if write_row {
-                result.push(row);
-            }
-        }
+        // rows hold the result columns followed by the sort keys
+        Ok(Box::new(
+            Self::skip_rows(rows, offset)
+                .take(limit.unwrap_or(usize::MAX))
+                .map(move |row| {
+                    row.map(|mut row| {
+                        row.truncate(num_columns);
+                        row
+                    })
+                }),
+        ))
+    }
 
-        Ok(result)
+    /// Skips the first `offset` rows, passing errors through.
+    fn skip_rows(rows: Rows<'_>, mut offset: usize) -> Rows<'_> {
+        Box::new(rows.filter(move |row| {
+            if offset == 0 || row.is_err() {
+                return true;
+            }
+            offset -= 1;
+            false
+        }))
     }
 
-    pub fn query_idx(&self, table_name: &str, looking_for: &String) -> Result<Option<Vec<i64>>> {
-        let idx_info = self
-            .idx_infos
+    fn table_info(&self, table_name: &str) -> Result<&TableInfo> {
+        if self.view_infos.contains_key(table_name) {
+            return Err(anyhow!("unsupported: joining view {table_name}"));
+        }
+        self.table_infos
             .get(table_name)
-            .ok_or(anyhow!("no index for {table_name}"))?;
-
-        let root_page = self.get_page(
-            (idx_info.root_page_num - 1) as u64 * self.header.page_size as u64,
-            None,
-        )?;
-
-        self._query_idx(root_page, looking_for)
+            .ok_or(anyhow!("no such table: {table_name}"))
     }
 
-    fn _query_idx(&self, root_page: Page, looking_for: &String) -> Result<Option<Vec<i64>>> {
-        match root_page {
-            Page::LeafTable(_) => panic!("index root page is LeafTablePage"),
-            Page::InteriorTable(_) => panic!("index root page is InteriorTablePage"),
-            Page::LeafIndex(leaf_idx_page) => self.query_leaf_idx(leaf_idx_page, looking_for),
-            Page::InteriorIdx(interior_idx_page) => {
-                self.query_interior_idx(interior_idx_page, looking_for)
+    /// Finds the rows of the FROM clause matching the WHERE clause and returns their
+    /// result columns followed by their sort keys, in ORDER BY order.
+    fn source_rows(&self, query: SelectQuery) -> Result<Rows<'_>> {
+        match query.joins.is_empty() {
+            true => {
+                let table_info = self.table_info(&query.table_name)?;
+                self.scan_rows(query, table_info)
             }
+            false => self.join_rows(query),
         }
     }
 
-    fn query_interior_idx(
-        &self,
-        page: InteriorIdxPage,
-        looking_for: &String,
-    ) -> Result<Option<Vec<i64>>> {
-        if page.cells.is_empty() {
-            panic!("page has no cells");
+    /// Finds the rows of the table matching the WHERE clause and returns their result
+    /// columns followed by their sort keys, in ORDER BY order.
+    fn scan_rows<'a>(&'a self, query: SelectQuery, table_info: &'a TableInfo) -> Result<Rows<'a>> {
+        let plan = self.plan_select(&query, table_info);
+        let order = Self::plan_order(&plan, &query, table_info);
+        let descending = order == Some(true);
+        let (num_columns, order_by) = (query.columns.len(), query.order_by.clone());
+
+        let rows = match plan {
+            ScanPlan::IdxSeek {
+                idx_info, range, ..
+            } => self.stream_idx_rows(query, table_info, idx_info, range, descending)?,
+            ScanPlan::RowidRange(rowids) => self.stream_rows(
+                query,
+                table_info,
+                move |key| locate_rowid(&rowids, key),
+                descending,
+            )?,
+            ScanPlan::TableScan if table_info.without_rowid => {
+                // seek by the conditions on a prefix of the primary key, in the order
+                // and by the collating sequences of its columns
+                let orders = KeyRange::orders(&table_info.primary_key);
+                let key = table_info.primary_key[..orders.len()].iter();
+                let values = Self::seek_values(key.map(|c| c.name.as_str()), &query, table_info);
+                let range = KeyRange::eq(values).with_orders(orders);
+                self.stream_rows(query, table_info, move |key| range.locate(key), descending)?
+            }
+            ScanPlan::TableScan => {
+                self.stream_rows(query, table_info, |_| Ordering::Equal, descending)?
+            }
         };
+        if order.is_some() {
+            return Ok(rows);
+        }
+
+        let mut rows = rows.collect::<Result<Vec<_>>>()?;
+        rows.sort_by(|a, b| Self::cmp_sort_keys(&order_by, &a[num_columns..], &b[num_columns..]));
+        Ok(Box::new(rows.into_iter().map(Ok)))
+    }
 
-        if page.cells.first().unwrap().record_body.columns.is_empty() {
-            panic!("no keys in idx");
+    /// Streams the matching rows of the entries of a table's b-tree that `range`
+    /// finds equal, in key order or backwards, reading a page at a time.
+    fn stream_rows<'a>(
+        &'a self,
+        query: impl Borrow<SelectQuery> + 'a,
+        table_info: &'a TableInfo,
+        range: impl Fn(Key) -> Ordering + 'a,
+        descending: bool,
+    ) -> Result<Rows<'a>> {
+        let root_page_num = table_info.root_page_num;
+        let mut cursor = match descending {
+            false => BTreeCursor::seek_ge(self, root_page_num, |key| range(key).is_lt())?,
+            true => BTreeCursor::seek_le(self, root_page_num, |key| range(key).is_gt())?,
         };
+        Ok(Box::new(std::iter::from_fn(move || loop {
+            if range(cursor.key()?).is_ne() {
+                return None;
+            }
+            let (rowid, record) = cursor.entry()?;
+            let row = Self::query_record(record, rowid, query.borrow(), table_info).transpose();
+            let moved = match descending {
+                false => cursor.next(),
+                true => cursor.prev(),
+            };
+            if let Err(e) = moved {
+                return Some(Err(e));
+            }
+            if row.is_some() {
+                return row;
+            }
+        })))
+    }
 
-        if page.cells.first().unwrap().record_body.columns.len() != 2 {
-            todo!("more than one key in index");
+    /// Streams the matching rows whose index entries are in `range`, in index order
+    /// or backwards, looking each one up in the table as its entry comes.
+    fn stream_idx_rows<'a>(
+        &'a self,
+        query: impl Borrow<SelectQuery> + 'a,
+        table_info: &'a TableInfo,
+        idx_info: &'a IdxInfo,
+        range: KeyRange,
+        descending: bool,
+    ) -> Result<Rows<'a>> {
+        let root_page_num = idx_info.root_page_num;
+        let mut entries = match descending {
+            false => BTreeCursor::seek_ge(self, root_page_num, |key| range.locate(key).is_lt())?,
+            true => BTreeCursor::seek_le(self, root_page_num, |key| range.locate(key).is_gt())?,
         };
+        let mut rows = BTreeCursor::first(self, table_info.root_page_num)?;
+        Ok(Box::new(std::iter::from_fn(move || loop {
+            let (_, record) = entries.entry()?;
+            if range.locate(Key::Record(record)).is_ne() {
+                return None;
+            }
+            // the rowid of the indexed row is the last column of the record
+            let rowid = record.last().and_then(Column::as_i64);
+            let moved = match descending {
+                false => entries.next(),
+                true => entries.prev(),
+            };
+            let row = moved
+                .and_then(|_| {
+                    let rowid = rowid.ok_or_else(|| {
+                        anyhow!("rowid in index {} is not int", idx_info.idx_name)
+                    })?;
+                    Self::lookup_row(&mut rows, rowid, query.borrow(), table_info)
+                })
+                .transpose();
+            if row.is_some() {
+                return row;
+            }
+        })))
+    }
 
-        let mut res = Vec::new();
+    /// Moves a table cursor to the row with `rowid` and returns it if it matches the
+    /// WHERE clause.
+    fn lookup_row(
+        cursor: &mut BTreeCursor,
+        rowid: i64,
+        query: &SelectQuery,
+        table_info: &TableInfo,
+    ) -> Result<Option<Vec<Column>>> {
+        cursor.reseek_ge(
+            |key| matches!(key, Key::Rowid(r) if r < rowid),
+            |key| matches!(key, Key::Rowid(r) if r > rowid),
+        )?;
+        match cursor.entry() {
+            // the index may cover only some of the conditions, check all of them
+            Some((Some(found), record)) if found == rowid => {
+                Self::query_record(record, Some(rowid), query, table_info)
+            }
+            _ => Ok(None),
+        }
+    }
 
-        let first_key = page
-            .cells
-            .first()
-            .unwrap()
-            .record_body
-            .columns
-            .first()
-            .unwrap();
+    /// Joins the tables of the FROM clause with nested loops and returns the result
+    /// columns followed by the sort keys of the combined rows that match.
+    fn join_rows(&self, query: SelectQuery) -> Result<Rows<'_>> {
+        let from = FromTables::new(self, &query)?;
 
-        let last_key = page
-            .cells
-            .last()
-            .unwrap()
-            .record_body
-            .columns
-            .first()
-            .unwrap();
+        // WHERE terms are checked as soon as the last table they use is bound
+        let mut where_terms = vec![vec![]; from.tables.len()];
+        for term in query.where_clause.iter().flat_map(Expr::conjuncts) {
+            let used = from.tables_used(term)?;
+            where_terms[used.last().copied().unwrap_or(0)].push((term, used));
+        }
 
-        if *first_key <= Column::Str(looking_for.clone())
-            && *last_key >= Column::Str(looking_for.clone())
-        {
-            for cell in page.cells {
-                if cell.record_body.columns.is_empty() {
-                    todo!("no keys in cell");
+        let mut levels = Vec::new();
+        for (i, (table, where_terms)) in from.tables.iter().zip(where_terms).enumerate() {
+            let join = i.checked_sub(1).map(|j| &query.joins[j]);
+            let left = join.is_some_and(|join| join.kind == JoinKind::Left);
+
+            // terms on this table alone filter its rows before the join, except WHERE
+            // terms of a LEFT JOIN, which also see the row of NULLs added for no match
+            let is_local = |used: &[usize]| used.iter().all(|t| *t == i);
+            let mut local = Vec::new();
+            let mut on = Vec::new();
+            let mut filters = Vec::new();
+            for term in join
+                .iter()
+                .flat_map(|join| &join.on)
+                .flat_map(Expr::conjuncts)
+            {
+                let used = from.tables_used(term)?;
+                if used.last().is_some_and(|last| *last > i) {
+                    return Err(anyhow!("ON clause references tables to its right"));
                 }
-
-                if cell.record_body.columns.len() != 2 {
-                    todo!("more than one key in index");
+                match is_local(&used) {
+                    true => local.push(term.map_columns(&|_, name| Ok(Expr::column(name)))?),
+                    false => on.push(term.clone()),
+                }
+            }
+            for (term, used) in where_terms {
+                match is_local(&used) && !left {
+                    true => local.push(term.map_columns(&|_, name| Ok(Expr::column(name)))?),
+                    false => filters.push(term.clone()),
                 }
+            }
+
+            // the rowid follows the columns of the table
+            let mut columns = table.info.column_orders.iter().collect::<Vec<_>>();
+            columns.sort_by_key(|(_, order)| **order);
+            let rowid = ROWID_NAMES
+                .into_iter()
+                .find(|name| table.info.is_rowid(name));
+            let scan_query = SelectQuery {
+                table_name: table.name.to_string(),
+                alias: None,
+                joins: vec![],
+                columns: columns
+                    .into_iter()
+                    .map(|(name, _)| name.as_str())
+                    .chain(rowid)
+                    .map(Expr::column)
+                    .collect(),
+                column_names: vec![],
+                aliases: vec![],
+                where_clause: local.into_iter().reduce(|lhs, rhs| Expr::Binary {
+                    op: BinaryOp::And,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                }),
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: 0,
+            };
 
-                let key = cell.record_body.columns.first().unwrap();
+            // an equality with the earlier tables is looked up in an index on this
+            // table for every combination, otherwise the table is scanned once
+            let mut seek_terms = on.iter().collect::<Vec<_>>();
+            if !left {
+                seek_terms.extend(&filters);
+            }
+            let seek = match i {
+                0 => None,
+                _ => self.plan_join_seek(&from, i, &seek_terms),
+            };
+            let rows = match (i, &seek) {
+                (0, _) | (_, Some(_)) => vec![],
+                (_, None) => self
+                    .scan_rows(scan_query.clone(), table.info)?
+                    .collect::<Result<_>>()?,
+            };
 
-                if *key == Column::Str(looking_for.clone()) {
-                    let rowid = match cell.record_body.columns.last().unwrap() {
-                        Column::I8(i) => *i as i64,
-                        Column::I16(i) => *i as i64,
-                        Column::I24(i) => *i as i64,
-                        _ => panic!("rowid is not int"),
+            levels.push(JoinLevel {
+                scan_query,
+                left,
+                on,
+                filters,
+                seek,
+                rows,
+            });
+        }
+
+        // without ORDER BY the rows come out in join order, so the LIMIT ends the join
+        let wanted = match (query.order_by.is_empty(), query.limit) {
+            (true, Some(limit)) => Some(query.offset.saturating_add(limit)),
+            _ => None,
+        };
+        // the rows of the first table are read as the join goes, and the combined
+        // rows of each of them come out before the next one is read
+        let mut outer = self.scan_rows(levels[0].scan_query.clone(), from.tables[0].info)?;
+        let (num_columns, order_by) = (query.columns.len(), query.order_by.clone());
+        let mut joined = Vec::new().into_iter();
+        let mut found = 0;
+        let rows: Rows<'_> = Box::new(std::iter::from_fn(move || loop {
+            if let Some(row) = joined.next() {
+                return Some(Ok(row));
+            }
+            let wanted = wanted.map(|wanted| wanted.saturating_sub(found));
+            if wanted == Some(0) {
+                return None;
+            }
+            let row = match outer.next()? {
+                Ok(row) => row,
+                Err(e) => return Some(Err(e)),
+            };
+            let mut res = Vec::new();
+            if let Err(e) =
+                self.join_level(&query, &from, &levels, &mut vec![row], wanted, &mut res)
+            {
+                return Some(Err(e));
+            }
+            found += res.len();
+            joined = res.into_iter();
+        }));
+        if order_by.is_empty() {
+            return Ok(rows);
+        }
+
+        let mut rows = rows.collect::<Result<Vec<_>>>()?;
+        rows.sort_by(|a, b| Self::cmp_sort_keys(&order_by, &a[num_columns..], &b[num_columns..]));
+        Ok(Box::new(rows.into_iter().map(Ok)))
+    }
+
+    /// Finds a term `column = expr` that fixes an indexed column of the `i`-th table
+    /// to a value computed from the tables before it.
+    fn plan_join_seek<'a>(
+        &'a self,
+        from: &FromTables,
+        i: usize,
+        terms: &[&Expr],
+    ) -> Option<JoinSeek<'a>> {
+        let table = &from.tables[i];
+        // secondary indexes of WITHOUT ROWID tables point to primary keys, not rowids
+        let idx_infos = match table.info.without_rowid {
+            true => vec![],
+            false => self.table_idx_infos(&table.name),
+        };
+
+        terms.iter().find_map(|term| {
+            let Expr::Binary {
+                op: BinaryOp::Eq,
+                lhs,
+                rhs,
+            } = term
+            else {
+                return None;
+            };
+            [(lhs, rhs), (rhs, lhs)]
+                .into_iter()
+                .find_map(|(column, value)| {
+                    let Expr::Column { table: t, name } = column.as_ref() else {
+                        return None;
                     };
-                    res.push(rowid);
+                    let (j, affinity) = from.resolve(t.as_deref(), name).ok()?;
+                    if j != i || from.tables_used(value).ok()?.last()? >= &i {
+                        return None;
+                    }
+                    // the index holds values converted to the column's affinity, which
+                    // the comparison only does to the other side under these conditions
+                    let is_numeric = matches!(
+                        affinity,
+                        Affinity::Integer | Affinity::Real | Affinity::Numeric
+                    );
+                    if let Expr::Column { table: t, name } = value.as_ref() {
+                        let (_, other) = from.resolve(t.as_deref(), name).ok()?;
+                        if other != affinity && !is_numeric {
+                            return None;
+                        }
+                    }
+                    let idx_info = match table.info.is_rowid(name) {
+                        true => None,
+                        false => Some(*idx_infos.iter().find(|idx_info| {
+                            idx_info.columns.first().is_some_and(|c| {
+                                c.name == *name && !c.descending && compares_binary(c)
+                            })
+                        })?),
+                    };
+                    Some(JoinSeek {
+                        idx_info,
+                        value: value.as_ref().clone(),
+                        affinity,
+                    })
+                })
+        })
+    }
+
+    /// Binds each matching row of the next table in turn and continues with the
+    /// tables after it, adding a result row once all of them are bound.
+    fn join_level(
+        &self,
+        query: &SelectQuery,
+        from: &FromTables,
+        levels: &[JoinLevel],
+        bound: &mut Vec<Vec<Column>>,
+        wanted: Option<usize>,
+        res: &mut Vec<Vec<Column>>,
+    ) -> Result<()> {
+        let Some(level) = levels.get(bound.len()) else {
+            let row = JoinRow { from, bound };
+            res.push(
+                query
+                    .columns
+                    .iter()
+                    .chain(query.order_by.iter().map(|term| &term.expr))
+                    .map(|expr| eval::eval(expr, &row))
+                    .collect::<Result<_>>()?,
+            );
+            return Ok(());
+        };
+        let holds = |terms: &[Expr], bound: &[Vec<Column>]| -> Result<bool> {
+            let row = JoinRow { from, bound };
+            for term in terms {
+                if !eval::is_true(&eval::eval(term, &row)?) {
+                    return Ok(false);
                 }
+            }
+            Ok(true)
+        };
 
-                let child_page = self.get_page(
-                    (cell.left_child_page_num - 1) as u64 * self.header.page_size as u64,
-                    None,
-                )?;
+        let sought;
+        let rows = match &level.seek {
+            Some(seek) => {
+                let value = eval::eval(&seek.value, &JoinRow { from, bound })?;
+                let value = seek.affinity.apply(value);
+                let table_info = from.tables[bound.len()].info;
+                sought = match (value, seek.idx_info) {
+                    (Column::Null, _) => vec![],
+                    // the rowid is an integer, no other value equals it
+                    (value, None) => {
+                        let rowid = match value {
+                            Column::F64(r) if r.fract() == 0.0 => Some(r as i64),
+                            value => value.as_i64(),
+                        };
+                        match rowid {
+                            Some(rowid) => self
+                                .stream_rows(
+                                    &level.scan_query,
+                                    table_info,
+                                    move |key| locate_rowid(&(rowid..=rowid), key),
+                                    false,
+                                )?
+                                .collect::<Result<_>>()?,
+                            None => vec![],
+                        }
+                    }
+                    (value, Some(idx_info)) => self
+                        .stream_idx_rows(
+                            &level.scan_query,
+                            table_info,
+                            idx_info,
+                            KeyRange::eq(vec![value]),
+                            false,
+                        )?
+                        .collect::<Result<_>>()?,
+                };
+                &sought
+            }
+            None => &level.rows,
+        };
 
-                if let Some(mut from_children) = self._query_idx(child_page, looking_for)? {
-                    res.append(&mut from_children);
+        let mut matched = false;
+        for row in rows {
+            if wanted.is_some_and(|wanted| res.len() >= wanted) {
+                return Ok(());
+            }
+            bound.push(row.clone());
+            if holds(&level.on, bound)? {
+                matched = true;
+                if holds(&level.filters, bound)? {
+                    self.join_level(query, from, levels, bound, wanted, res)?;
                 }
             }
-        } else {
-            let offset = (page.page_header.rightmost_pointer.unwrap() - 1) as u64
-                * self.header.page_size as u64;
-            let rightmost_page = self
-                .get_page(offset, None)
-                .map_err(|e| anyhow!("can't get rightmost page: {e}"))?;
+            bound.pop();
+        }
 
-            if let Some(mut from_rightmost) = self._query_idx(rightmost_page, looking_for)? {
-                res.append(&mut from_rightmost);
+        // a LEFT JOIN keeps the row without a match, with NULLs for the joined table
+        if level.left && !matched {
+            bound.push(vec![Column::Null; level.scan_query.columns.len()]);
+            if holds(&level.filters, bound)? {
+                self.join_level(query, from, levels, bound, wanted, res)?;
             }
+            bound.pop();
         }
 
-        Ok(Some(res))
+        Ok(())
     }
 
-    fn query_leaf_idx(&self, page: LeafIdxPage, looking_for: &String) -> Result<Option<Vec<i64>>> {
-        if page.cells.is_empty() {
-            panic!("page has no cells");
-        };
+    /// Groups the matching rows by the GROUP BY terms and returns the result columns
+    /// followed by the sort keys of each group that passes HAVING, in ORDER BY order.
+    /// Unlike plain selects, this reads every matching row before returning, as an
+    /// aggregate needs all rows of its group and the groups come out sorted.
+    fn select_groups(&self, query: &SelectQuery) -> Result<Vec<Vec<Column>>> {
+        let aggregates = query.aggregates();
+        let bare_columns = query.bare_columns();
+        let from = FromTables::new(self, query)?;
+        let affinities = bare_columns
+            .iter()
+            .map(|c| match c {
+                Expr::Column { table, name } => from.affinity(table.as_deref(), name),
+                _ => Affinity::Blob,
+            })
+            .collect::<Vec<_>>();
+
+        // COUNT(*) of a whole table only needs the number of cells in its pages, and
+        // MIN() or MAX() of a column a b-tree is ordered by is at one of its ends
+        if let [Expr::Function {
+            name,
+            distinct: false,
+            args,
+        }] = aggregates.as_slice()
+        {
+            if bare_columns.is_empty()
+                && query.where_clause.is_none()
+                && query.group_by.is_empty()
+                && query.joins.is_empty()
+            {
+                let table_info = self.table_info(&query.table_name)?;
+                let value = match (name.as_str(), args.as_slice()) {
+                    ("count", []) => Some(Column::I64(BTreeCursor::count(
+                        self,
+                        table_info.root_page_num,
+                    )?)),
+                    (
+                        "min" | "max",
+                        [Expr::Column {
+                            table,
+                            name: column,
+                        }],
+                    ) => {
+                        from.resolve(table.as_deref(), column)?;
+                        self.end_value(&query.table_name, table_info, column, name == "max")?
+                    }
+                    _ => None,
+                };
+                if let Some(value) = value {
+                    let group_row = GroupRow {
+                        aggregates: &aggregates,
+                        values: vec![value],
+                        bare_columns: &bare_columns,
+                        affinities: &affinities,
+                        row: None,
+                    };
+                    return Ok(Self::group_result(query, &group_row)?.into_iter().collect());
+                }
+            }
+        }
 
-        if page.cells.first().unwrap().record_body.columns.is_empty() {
-            panic!("no keys in idx");
+        // each scanned row holds the GROUP BY terms, the arguments of every aggregate
+        // and the columns used outside of aggregates, sorted by the GROUP BY terms
+        let mut inputs = query.group_by.clone();
+        for call in &aggregates {
+            if let Expr::Function { args, .. } = call {
+                inputs.extend(args.iter().cloned());
+            }
+        }
+        inputs.extend(bare_columns.iter().map(|c| (*c).clone()));
+        let scan_query = SelectQuery {
+            columns: inputs,
+            column_names: vec![],
+            aliases: vec![],
+            group_by: vec![],
+            having: None,
+            order_by: query
+                .group_by
+                .iter()
+                .map(|expr| OrderingTerm {
+                    expr: expr.clone(),
+                    descending: false,
+                    nulls: None,
+                })
+                .collect(),
+            limit: None,
+            offset: 0,
+            ..query.clone()
         };
+        let bare_start = scan_query.columns.len() - bare_columns.len();
+        let rows = self.source_rows(scan_query)?.collect::<Result<Vec<_>>>()?;
+
+        let num_keys = query.group_by.len();
+        let mut groups: Vec<&[Vec<Column>]> = rows
+            .chunk_by(|a, b| {
+                a[..num_keys]
+                    .iter()
+                    .zip(&b[..num_keys])
+                    .all(|(a, b)| a.compare(b).is_eq())
+            })
+            .collect();
+        // without GROUP BY all rows make one group, even if there are none
+        if num_keys == 0 && groups.is_empty() {
+            groups.push(&[]);
+        }
 
-        if page.cells.first().unwrap().record_body.columns.len() != 2 {
-            todo!("more than one key in index");
+        // a single MIN or MAX picks the row the other columns come from
+        let extreme = match aggregates.as_slice() {
+            [Expr::Function { name, .. }] if name == "min" || name == "max" => Some(0),
+            _ => None,
         };
 
         let mut res = Vec::new();
-
-        //let first_key = page.cells.first().unwrap();
-        //let first_key = first_key.record_body.columns.first().unwrap();
-        //let first_key = match first_key {
-        //    Column::Str(s) => s,
-        //    k => todo!("key is not str: {k}"),
-        //};
-        //
-        //if first_key > looking_for {
-        //    return Ok(None);
-        //};
-        //
-        //let last_key = match page
-        //    .cells
-        //    .last()
-        //    .unwrap()
-        //    .record_body
-        //    .columns
-        //    .first()
-        //    .unwrap()
-        //{
-        //    Column::Str(s) => s,
-        //    _ => todo!("key is not str"),
-        //};
-        //
-        //if (first_key > looking_for && last_key > looking_for) || last_key < looking_for {
-        //    return Ok(None);
-        //};
-
-        for cell in &page.cells {
-            if cell.record_body.columns.is_empty() {
-                todo!("no keys in cell");
-            }
-
-            if cell.record_body.columns.len() != 2 {
-                todo!("more than one key in index");
-            }
-
-            let key = cell.record_body.columns.first().unwrap();
-
-            if *key == Column::Str(looking_for.clone()) {
-                let rowid = match cell.record_body.columns.last().unwrap() {
-                    Column::I8(i) => *i as i64,
-                    Column::I16(i) => *i as i64,
-                    Column::I24(i) => *i as i64,
-                    _ => panic!("rowid is not int"),
-                };
-                res.push(rowid);
+        for group in groups {
+            let mut accumulators = aggregates
+                .iter()
+                .map(|call| Accumulator::new(call))
+                .collect::<Result<Vec<_>>>()?;
+            let mut row = group.first();
+            for r in group {
+                let mut args = &r[num_keys..];
+                for (i, (call, accumulator)) in aggregates.iter().zip(&mut accumulators).enumerate()
+                {
+                    let Expr::Function {
+                        args: call_args, ..
+                    } = call
+                    else {
+                        unreachable!()
+                    };
+                    if accumulator.step(&args[..call_args.len()]) && extreme == Some(i) {
+                        row = Some(r);
+                    }
+                    args = &args[call_args.len()..];
+                }
             }
+
+            let group_row = GroupRow {
+                aggregates: &aggregates,
+                values: accumulators
+                    .into_iter()
+                    .map(Accumulator::finish)
+                    .collect::<Result<_>>()?,
+                bare_columns: &bare_columns,
+                affinities: &affinities,
+                row: row.map(|r| &r[bare_start..bare_start + bare_columns.len()]),
+            };
+
+            res.extend(Self::group_result(query, &group_row)?);
         }
 
-        Ok(Some(res))
+        let num_columns = query.columns.len();
+        res.sort_by(|a, b| {
+            Self::cmp_sort_keys(&query.order_by, &a[num_columns..], &b[num_columns..])
+        });
+
+        Ok(res)
     }
 
-    pub fn get_row(
-        &self,
-        page: &Page,
-        rowid: i64,
-        table_info: &TableInfo,
-        query: &SelectQuery,
-    ) -> Result<Vec<String>> {
-        match page {
-            Page::LeafTable(leaf_page) => self.get_row_leaf(leaf_page, rowid, table_info, query),
-            Page::InteriorTable(interior_page) => {
-                self.get_row_interior(interior_page, rowid, table_info, query)
+    /// Returns the result columns followed by the sort keys of a group, or `None` if
+    /// it doesn't pass HAVING.
+    fn group_result(query: &SelectQuery, group_row: &GroupRow) -> Result<Option<Vec<Column>>> {
+        if let Some(having) = &query.having {
+            if !eval::is_true(&eval::eval(having, group_row)?) {
+                return Ok(None);
             }
-            _ => panic!("can't get row from an index page"),
         }
+
+        query
+            .columns
+            .iter()
+            .chain(query.order_by.iter().map(|term| &term.expr))
+            .map(|expr| eval::eval(expr, group_row))
+            .collect::<Result<_>>()
+            .map(Some)
     }
 
-    fn get_row_interior(
+    /// Returns the smallest or, with `max`, the largest value of a column from the
+    /// first or last entry of a b-tree ordered by it, if the table has one.
+    fn end_value(
         &self,
-        page: &InteriorTablePage,
-        rowid: i64,
+        table_name: &str,
         table_info: &TableInfo,
-        query: &SelectQuery,
-    ) -> Result<Vec<String>> {
-        //let first_rowid = page.cells.first().unwrap().rowid;
-        let last_rowid = page.cells.last().unwrap().rowid;
-
-        if last_rowid >= rowid {
-            for cell in &page.cells {
-                if rowid <= cell.rowid {
-                    let page = self.get_page(
-                        (cell.left_child_page_num - 1) as u64 * self.header.page_size as u64,
-                        None,
-                    )?;
-                    return self.get_row(&page, rowid, table_info, query);
+        column_name: &str,
+        max: bool,
+    ) -> Result<Option<Column>> {
+        if table_info.is_rowid(column_name) {
+            let cursor = match max {
+                false => BTreeCursor::first(self, table_info.root_page_num)?,
+                true => BTreeCursor::last(self, table_info.root_page_num)?,
+            };
+            let rowid = cursor.entry().and_then(|(rowid, _)| rowid);
+            return Ok(Some(rowid.map_or(Column::Null, Column::I64)));
+        }
+
+        // the order of the b-tree must be that of comparing the values
+        let root_page_num = match table_info.primary_key.first() {
+            Some(first)
+                if table_info.without_rowid
+                    && first.name == column_name
+                    && !first.descending
+                    && compares_binary(first) =>
+            {
+                table_info.root_page_num
+            }
+            _ => {
+                let idx_info = self.table_idx_infos(table_name).into_iter().find(|idx| {
+                    idx.columns.first().is_some_and(|c| {
+                        c.name == column_name && !c.descending && compares_binary(c)
+                    })
+                });
+                match idx_info {
+                    Some(idx_info) => idx_info.root_page_num,
+                    None => return Ok(None),
                 }
             }
+        };
+        // NULLs come first and don't count, MAX() is NULL only if all of them are
+        let cursor = match max {
+            false => BTreeCursor::seek_ge(self, root_page_num, |key| {
+                matches!(key, Key::Record([Column::Null, ..]))
+            })?,
+            true => BTreeCursor::last(self, root_page_num)?,
+        };
+        let value = cursor.entry().and_then(|(_, record)| record.first());
+        Ok(Some(value.map_or(Column::Null, |value| {
+            table_info.column_value(column_name, value).into_owned()
+        })))
+    }
+
+    /// Merges the outer query into the stored SELECT of a view and runs it.
+    fn select_view_rows(&self, view_info: &ViewInfo, mut query: SelectQuery) -> Result<Rows<'_>> {
+        let (mut view_query, view_columns) = self.view_query(view_info)?;
+        self.resolve_aliases(&mut view_query)?;
+        self.resolve_aliases(&mut query)?;
+
+        let view_is_aggregate = view_query.is_aggregate();
+        let view_is_limited = view_query.limit.is_some() || view_query.offset > 0;
+        // the outer query only runs as part of the view's own SELECT, which can't
+        // take an outer condition after its LIMIT or group the groups it makes
+        let name = &view_info.view_name;
+        if query.is_aggregate() && view_is_aggregate {
+            return Err(anyhow!(
+                "unsupported: aggregating the rows of view {name}, which has aggregates"
+            ));
+        }
+        if view_is_limited && query.is_aggregate() {
+            return Err(anyhow!(
+                "unsupported: aggregating the rows of view {name}, which has a LIMIT"
+            ));
+        }
+        if view_is_limited && (query.where_clause.is_some() || !query.order_by.is_empty()) {
+            return Err(anyhow!(
+                "unsupported: filtering or sorting the rows of view {name}, which has a LIMIT"
+            ));
+        }
+
+        // the outer query works on the expressions of the view's columns
+        let underlying = |_: Option<&str>, name: &str| {
+            view_columns
+                .iter()
+                .position(|c| c == name)
+                .map(|i| view_query.columns[i].clone())
+                .ok_or(anyhow!("no such column: {name}"))
+        };
+        let map = |expr: &Expr| expr.map_columns(&underlying);
+
+        let columns = query.columns.iter().map(map).collect::<Result<_>>()?;
+        // the outer WHERE is checked together with the view's own, or with its HAVING
+        // if the view groups its rows
+        let where_clause = query.where_clause.as_ref().map(map).transpose()?;
+        let group_by = query.group_by.iter().map(map).collect::<Result<Vec<_>>>()?;
+        let having = query.having.as_ref().map(map).transpose()?;
+        // the outer ORDER BY replaces the view's own
+        let order_by = query
+            .order_by
+            .iter()
+            .map(|term| {
+                Ok(OrderingTerm {
+                    expr: map(&term.expr)?,
+                    ..term.clone()
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let and = |lhs: Option<Expr>, rhs: Option<Expr>| match (lhs, rhs) {
+            (Some(lhs), Some(rhs)) => Some(Expr::Binary {
+                op: BinaryOp::And,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            }),
+            (lhs, rhs) => lhs.or(rhs),
+        };
+        if view_is_aggregate {
+            view_query.having = and(view_query.having, where_clause);
         } else {
-            let page = self.get_page(
-                (page.page_header.rightmost_pointer.unwrap() - 1) as u64
-                    * self.header.page_size as u64,
-                None,
-            )?;
-            return self.get_row(&page, rowid, table_info, query);
+            view_query.where_clause = and(view_query.where_clause, where_clause);
+            if query.is_aggregate() {
+                view_query.group_by = group_by;
+                view_query.having = having;
+            }
+        }
+        if !order_by.is_empty() {
+            view_query.order_by = order_by;
+        }
+        view_query.columns = columns;
+        view_query.column_names = query.column_names;
+        view_query.aliases = query.aliases;
+
+        // a view without its own LIMIT lets the outer one stop the scan
+        let (mut limit, mut offset) = (query.limit, query.offset);
+        if !view_is_limited {
+            view_query.limit = limit.take();
+            view_query.offset = std::mem::take(&mut offset);
         }
 
-        Ok(vec![])
+        let rows = self.select_rows(view_query)?;
+        Ok(Box::new(
+            Self::skip_rows(rows, offset).take(limit.unwrap_or(usize::MAX)),
+        ))
     }
 
-    fn get_row_leaf(
-        &self,
-        page: &LeafTablePage,
-        rowid: i64,
-        table_info: &TableInfo,
+    /// Parses the stored SELECT of a view and returns it with the names of the
+    /// view's columns.
+    fn view_query(&self, view_info: &ViewInfo) -> Result<(SelectQuery, Vec<String>)> {
+        let view_query = SelectQuery::from_query_string(&view_info.select_sql)
+            .map_err(|e| anyhow!("can't parse view {}: {e}", view_info.view_name))?;
+
+        let mut view_columns = view_query.column_names.clone();
+        if let Some(columns) = &view_info.columns {
+            if columns.len() != view_columns.len() {
+                return Err(anyhow!(
+                    "expected {} columns for '{}' but got {}",
+                    columns.len(),
+                    view_info.view_name,
+                    view_columns.len()
+                ));
+            }
+            view_columns = columns.clone();
+        }
+        Ok((view_query, view_columns))
+    }
+
+    /// Resolves the result column aliases in GROUP BY and HAVING, which only stand
+    /// for the result column where no column of the FROM clause has their name.
+    fn resolve_aliases(&self, query: &mut SelectQuery) -> Result<()> {
+        if !query.is_aggregate() {
+            return Ok(());
+        }
+        match self.view_infos.get(&query.table_name) {
+            Some(view_info) if query.joins.is_empty() => {
+                let (_, columns) = self.view_query(view_info)?;
+                query.resolve_aliases(|_, name| columns.iter().any(|c| c == name))
+            }
+            _ => {
+                let from = FromTables::new(self, query)?;
+                query.resolve_aliases(|table, name| from.resolve(table, name).is_ok())
+            }
+        }
+    }
+
+    /// Builds the output row for one record of the table followed by its ORDER BY
+    /// sort keys, or `None` if the record doesn't match the WHERE clause.
+    fn query_record(
+        columns: &[Column],
+        rowid: Option<i64>,
         query: &SelectQuery,
-    ) -> Result<Vec<String>> {
-        let mut row = vec![String::from(""); query.columns.len()];
-        for cell in &page.cells {
-            if cell.rowid == rowid {
-                for column_name in table_info.column_orders.keys() {
-                    let order = table_info.column_orders[column_name];
-                    let column = &cell.record_body.columns[order];
-
-                    let column_value = match column {
-                        Column::Str(s) => s.to_string(),
-                        Column::I8(i) => i.to_string(),
-                        Column::I16(i) => i.to_string(),
-                        Column::I24(i) => i.to_string(),
-                        Column::Zero => String::from("0"),
-                        Column::One => String::from("1"),
-                        Column::Null => cell.rowid.to_string(),
-                    };
+        table_info: &TableInfo,
+    ) -> Result<Option<Vec<Column>>> {
+        let value = |column_name: &str| {
+            // the INTEGER PRIMARY KEY column is stored as NULL, it holds the rowid
+            if let Some(rowid) = rowid.filter(|_| table_info.is_rowid(column_name)) {
+                return Some((Column::I64(rowid), Affinity::Integer));
+            }
+            let order = *table_info.column_orders.get(column_name)?;
+            // records written before ALTER TABLE ADD COLUMN are shorter
+            let column = columns.get(order).unwrap_or(&Column::Null);
+            let column = table_info.column_value(column_name, column).into_owned();
+            let affinity = table_info
+                .column_affinities
+                .get(column_name)
+                .copied()
+                .unwrap_or(Affinity::Blob);
+            Some((column, affinity))
+        };
+
+        if let Some(where_clause) = &query.where_clause {
+            if !eval::is_true(&eval::eval(where_clause, &value)?) {
+                return Ok(None);
+            }
+        }
+
+        let row = query
+            .columns
+            .iter()
+            .chain(query.order_by.iter().map(|term| &term.expr))
+            .map(|expr| eval::eval(expr, &value))
+            .collect::<Result<_>>()?;
+
+        Ok(Some(row))
+    }
+}
+
+/// The number of pages the cache keeps, 2 MiB worth of 4096-byte pages like the
+/// default cache of sqlite3.
+const PAGE_CACHE_SIZE: usize = 512;
+
+/// The most recently used pages, so that cursors coming back to a page don't read
+/// and decode it again. It never holds more than `PAGE_CACHE_SIZE` of them.
+#[derive(Default)]
+struct PageCache {
+    pages: BTreeMap<u32, (Rc<Page>, u64)>, // PageNum->(Page, LastUse)
+    uses: BTreeMap<u64, u32>,              // LastUse->PageNum
+    clock: u64,
+}
+
+impl PageCache {
+    fn get(&mut self, page_num: u32) -> Option<Rc<Page>> {
+        let (page, last_use) = self.pages.get_mut(&page_num)?;
+        self.uses.remove(last_use);
+        self.clock += 1;
+        *last_use = self.clock;
+        self.uses.insert(self.clock, page_num);
+        Some(page.clone())
+    }
+
+    fn insert(&mut self, page_num: u32, page: Rc<Page>) {
+        if self.pages.len() >= PAGE_CACHE_SIZE {
+            if let Some((_, least_used)) = self.uses.pop_first() {
+                self.pages.remove(&least_used);
+            }
+        }
+        self.clock += 1;
+        self.uses.insert(self.clock, page_num);
+        if let Some((_, last_use)) = self.pages.insert(page_num, (page, self.clock)) {
+            self.uses.remove(&last_use);
+        }
+    }
+}
+
+/// A page read into memory at once, standing in for the file while its cells are
+/// parsed. Reads outside of it, like those of overflow pages, go to the file.
+struct PageBuf<'a> {
+    file: &'a File,
+    offset: u64,
+    data: Vec<u8>,
+}
+
+impl<'a> PageBuf<'a> {
+    fn read(file: &'a File, offset: u64, size: usize) -> std::io::Result<Self> {
+        let mut data = vec![0; size];
+        file.read_exact_at(&mut data, offset)?;
+        Ok(PageBuf { file, offset, data })
+    }
+}
+
+impl FileExt for PageBuf<'_> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> std::io::Result<usize> {
+        let start = offset.checked_sub(self.offset).map(|start| start as usize);
+        match start.and_then(|start| self.data.get(start..)) {
+            Some(rest) if !rest.is_empty() => {
+                let n = buf.len().min(rest.len());
+                buf[..n].copy_from_slice(&rest[..n]);
+                Ok(n)
+            }
+            _ => self.file.read_at(buf, offset),
+        }
+    }
+
+    fn write_at(&self, _buf: &[u8], _offset: u64) -> std::io::Result<usize> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// How the rows of a SELECT are found.
+enum ScanPlan<'a> {
+    /// Look up rowids in a range of the keys of an index whose first `fixed` columns
+    /// are equal to one value.
+    IdxSeek {
+        idx_info: &'a IdxInfo,
+        fixed: usize,
+        range: KeyRange,
+    },
+    /// Visit the rows of a table whose rowids are in the range.
+    RowidRange(RangeInclusive<i64>),
+    /// Visit every row of the table.
+    TableScan,
+}
+
+/// The keys of an index between two bounds, each a prefix of the key columns that
+/// compares equal to every key starting with it.
+struct KeyRange {
+    start: Bound<Vec<Column>>,
+    end: Bound<Vec<Column>>,
+    // whether each key column is descending and its collating sequence, the columns
+    // past the end are ascending and BINARY
+    orders: Vec<(bool, Collation)>,
+}
+
+impl KeyRange {
+    /// Returns the keys starting with `prefix`.
+    fn eq(prefix: Vec<Column>) -> KeyRange {
+        KeyRange {
+            start: Bound::Included(prefix.clone()),
+            end: Bound::Included(prefix),
+            orders: vec![],
+        }
+    }
+
+    /// Returns the orders of the leading key columns with a built-in collating
+    /// sequence, which are the ones a range can compare.
+    fn orders(columns: &[IndexedColumn]) -> Vec<(bool, Collation)> {
+        columns
+            .iter()
+            .map_while(|c| Some((c.descending, Collation::from_name(c.collation.as_deref())?)))
+            .collect()
+    }
+
+    /// Returns the range comparing the key columns in the given orders.
+    fn with_orders(self, orders: Vec<(bool, Collation)>) -> KeyRange {
+        KeyRange { orders, ..self }
+    }
+
+    /// Compares the leading columns of an index record to a bound, in key order.
+    fn cmp_key(&self, record: &[Column], key: &[Column]) -> Ordering {
+        record
+            .iter()
+            .zip(key)
+            .enumerate()
+            .map(|(i, (column, value))| {
+                let (descending, collation) = self
+                    .orders
+                    .get(i)
+                    .copied()
+                    .unwrap_or((false, Collation::Binary));
+                let ordering = collation.compare(column, value);
+                match descending {
+                    true => ordering.reverse(),
+                    false => ordering,
+                }
+            })
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+
+    fn is_before(&self, record: &[Column]) -> bool {
+        match &self.start {
+            Bound::Included(key) => self.cmp_key(record, key).is_lt(),
+            Bound::Excluded(key) => self.cmp_key(record, key).is_le(),
+            Bound::Unbounded => false,
+        }
+    }
+
+    fn is_after(&self, record: &[Column]) -> bool {
+        match &self.end {
+            Bound::Included(key) => self.cmp_key(record, key).is_gt(),
+            Bound::Excluded(key) => self.cmp_key(record, key).is_ge(),
+            Bound::Unbounded => false,
+        }
+    }
+
+    /// Tells if the key of an index entry is before the range, in it or after it.
+    fn locate(&self, key: Key) -> Ordering {
+        match key {
+            Key::Record(record) if self.is_before(record) => Ordering::Less,
+            Key::Record(record) if self.is_after(record) => Ordering::Greater,
+            _ => Ordering::Equal,
+        }
+    }
+}
 
-                    if query.columns.contains_key(column_name) {
-                        row[*query.columns.get(column_name).unwrap()] = column_value;
+/// Tells if an index orders the values of a column like comparing them does, which
+/// is by the BINARY collating sequence.
+fn compares_binary(column: &IndexedColumn) -> bool {
+    Collation::from_name(column.collation.as_deref()) == Some(Collation::Binary)
+}
+
+/// Tells if the key of a table entry is before a range of rowids, in it or after it.
+fn locate_rowid(rowids: &RangeInclusive<i64>, key: Key) -> Ordering {
+    match key {
+        Key::Rowid(rowid) if rowid < *rowids.start() => Ordering::Less,
+        Key::Rowid(rowid) if rowid > *rowids.end() => Ordering::Greater,
+        _ => Ordering::Equal,
+    }
+}
+
+/// Replaces `bound` by `new` if it's tighter: for lower bounds with `Ordering::Greater`
+/// if it's greater, for upper bounds with `Ordering::Less` if it's less.
+fn tighten(bound: &mut Bound<Column>, new: Bound<Column>, tighter: Ordering) {
+    let is_tighter = match (&*bound, &new) {
+        (Bound::Unbounded, _) => true,
+        (_, Bound::Unbounded) => false,
+        (
+            Bound::Included(old) | Bound::Excluded(old),
+            Bound::Included(value) | Bound::Excluded(value),
+        ) => match value.compare(old) {
+            Ordering::Equal => matches!(new, Bound::Excluded(_)),
+            ordering => ordering == tighter,
+        },
+    };
+    if is_tighter {
+        *bound = new;
+    }
+}
+
+/// A table of the FROM clause.
+struct FromTable<'a> {
+    name: String,
+    // the alias, or the name without one
+    qualifier: String,
+    info: &'a TableInfo,
+}
+
+/// The tables of the FROM clause, which the columns of a query refer to.
+struct FromTables<'a> {
+    tables: Vec<FromTable<'a>>,
+}
+
+impl<'a> FromTables<'a> {
+    fn new(db: &'a Db, query: &SelectQuery) -> Result<FromTables<'a>> {
+        let first = FromTable {
+            name: query.table_name.clone(),
+            qualifier: query.alias.clone().unwrap_or(query.table_name.clone()),
+            info: db.table_info(&query.table_name)?,
+        };
+        let mut tables = vec![first];
+        for join in &query.joins {
+            tables.push(FromTable {
+                name: join.table.name.clone(),
+                qualifier: join.table.qualifier().to_string(),
+                info: db.table_info(&join.table.name)?,
+            });
+        }
+        Ok(FromTables { tables })
+    }
+
+    /// Returns the position of the table a column belongs to and the column's affinity.
+    fn resolve(&self, table: Option<&str>, name: &str) -> Result<(usize, Affinity)> {
+        let mut found = self
+            .tables
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| table.map_or(true, |table| t.qualifier == table))
+            .filter_map(|(i, t)| match t.info.column_affinities.get(name) {
+                Some(affinity) => Some((i, *affinity)),
+                None => t.info.is_rowid(name).then_some((i, Affinity::Integer)),
+            });
+        match (found.next(), found.next(), table) {
+            (Some(found), None, _) => Ok(found),
+            (Some(_), Some(_), _) => Err(anyhow!("ambiguous column name: {name}")),
+            (None, _, Some(table)) => Err(anyhow!("no such column: {table}.{name}")),
+            (None, _, None) => Err(anyhow!("no such column: {name}")),
+        }
+    }
+
+    /// Returns the affinity of a column, none if it isn't a column of the tables.
+    fn affinity(&self, table: Option<&str>, name: &str) -> Affinity {
+        self.resolve(table, name)
+            .map_or(Affinity::Blob, |(_, affinity)| affinity)
+    }
+
+    /// Returns the positions of the tables the columns of `expr` belong to, in order.
+    fn tables_used(&self, expr: &Expr) -> Result<Vec<usize>> {
+        let mut used = Vec::new();
+        let mut error = None;
+        expr.visit(&mut |e| {
+            if let Expr::Column { table, name } = e {
+                match self.resolve(table.as_deref(), name) {
+                    Ok((i, _)) if !used.contains(&i) => used.push(i),
+                    Ok(_) => {}
+                    Err(e) => {
+                        error.get_or_insert(e);
                     }
                 }
-                return Ok(row);
+            }
+            true
+        });
+        match error {
+            Some(e) => Err(e),
+            None => {
+                used.sort();
+                Ok(used)
             }
         }
+    }
+}
+
+/// One table of a join, and the conditions its rows must meet.
+struct JoinLevel<'a> {
+    // finds the rows of the table alone with the conditions on it alone
+    scan_query: SelectQuery,
+    left: bool,
+    // ON terms that also use earlier tables
+    on: Vec<Expr>,
+    // WHERE terms for which this is the last table bound
+    filters: Vec<Expr>,
+    seek: Option<JoinSeek<'a>>,
+    // all rows of `scan_query` if there is no seek, except for the first table,
+    // whose rows are read one at a time
+    rows: Vec<Vec<Column>>,
+}
+
+/// A lookup of the rows of a joined table through an index on one of its columns.
+struct JoinSeek<'a> {
+    // None for the rowid, which the table itself is keyed by
+    idx_info: Option<&'a IdxInfo>,
+    // the value of the column, computed from the earlier tables
+    value: Expr,
+    affinity: Affinity,
+}
 
-        Ok(vec![])
+/// The rows bound so far to the tables of a join.
+struct JoinRow<'a> {
+    from: &'a FromTables<'a>,
+    bound: &'a [Vec<Column>],
+}
+
+impl eval::Row for JoinRow<'_> {
+    fn column(&self, table: Option<&str>, name: &str) -> Option<(Column, Affinity)> {
+        let (i, affinity) = self.from.resolve(table, name).ok()?;
+        let column_orders = &self.from.tables[i].info.column_orders;
+        let order = column_orders
+            .get(name)
+            .copied()
+            .unwrap_or(column_orders.len());
+        let value = self.bound.get(i)?.get(order)?.clone();
+        Some((value, affinity))
+    }
+}
+
+/// The values of a group of rows: the results of its aggregates and the columns of
+/// the row that represents it.
+struct GroupRow<'a> {
+    aggregates: &'a [&'a Expr],
+    values: Vec<Column>,
+    bare_columns: &'a [&'a Expr],
+    affinities: &'a [Affinity],
+    // None if the group has no rows
+    row: Option<&'a [Column]>,
+}
+
+impl eval::Row for GroupRow<'_> {
+    fn column(&self, table: Option<&str>, name: &str) -> Option<(Column, Affinity)> {
+        let i = self.bare_columns.iter().position(|c| {
+            matches!(c, Expr::Column { table: t, name: n } if t.as_deref() == table && n == name)
+        })?;
+        let value = self.row.map_or(Column::Null, |row| row[i].clone());
+        Some((value, self.affinities[i]))
+    }
+
+    fn aggregate(&self, call: &Expr) -> Option<Column> {
+        let i = self.aggregates.iter().position(|a| *a == call)?;
+        Some(self.values[i].clone())
     }
 }
 
-pub struct DbHeader {
-    pub page_size: u16,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn select(db: &Db, sql: &str) -> Vec<Vec<Value>> {
+        let query = SelectQuery::from_query_string(sql).unwrap();
+        db.execute_select(query)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap()
+    }
+
+    fn text(s: &str) -> Value {
+        Value::Text(s.to_string())
+    }
+
+    /// Returns the name of the index the query searches and the number of its
+    /// columns fixed to one value, if it uses one.
+    fn idx_plan(db: &Db, sql: &str) -> Option<(String, usize)> {
+        let query = SelectQuery::from_query_string(sql).unwrap();
+        match db.plan_select(&query, db.table_info(&query.table_name).unwrap()) {
+            ScanPlan::IdxSeek {
+                idx_info, fixed, ..
+            } => Some((idx_info.idx_name.to_string(), fixed)),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the rows the query finds come in ORDER BY order, as
+    /// `Db::plan_order` tells it.
+    fn order_plan(db: &Db, sql: &str) -> Option<bool> {
+        let query = SelectQuery::from_query_string(sql).unwrap();
+        let table_info = db.table_info(&query.table_name).unwrap();
+        Db::plan_order(&db.plan_select(&query, table_info), &query, table_info)
+    }
+
+    /// Returns the range of rowids the query looks up, if it looks them up directly.
+    fn rowid_plan(db: &Db, sql: &str) -> Option<RangeInclusive<i64>> {
+        let query = SelectQuery::from_query_string(sql).unwrap();
+        match db.plan_select(&query, db.table_info(&query.table_name).unwrap()) {
+            ScanPlan::RowidRange(rowids) => Some(rowids),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn reads_overflow_pages() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        // the record of 3008 bytes keeps 38 of them on the leaf and spills over six
+        // overflow pages, as test.db has 512-byte pages with 504 usable bytes
+        let body = (1..=600)
+            .map(|i| format!("{i:04}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let root_page_num = db.table_infos["notes"].root_page_num;
+        let page_offset = (root_page_num - 1) as u64 * db.header.page_size as u64;
+        match db.get_page(page_offset, None).unwrap() {
+            Page::LeafTable(leaf) => {
+                assert_eq!(leaf.cells[1].record_body.columns[2], Column::Str(body))
+            }
+            page => unreachable!("notes fits on one leaf page, got {page:?}"),
+        }
+    }
+
+    #[test]
+    fn skips_reserved_bytes() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        assert_eq!(db.header.usable_size(), 504);
+        // page 2 holds the leaf of notes, which ends in a marker in its reserved bytes
+        assert_eq!(db.get_reserved_bytes(2).unwrap(), b"reserved");
+        assert_eq!(db.get_reserved_bytes(1).unwrap(), [0; 8]);
+        assert!(db.get_reserved_bytes(0).is_err());
+    }
+
+    #[test]
+    fn reads_schema_pages() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        // the tables t01 to t30 of test.db don't fit on page 1, which becomes an
+        // interior page
+        let page_header = Db::get_page_header(&db.file, 100).unwrap();
+        assert_eq!(page_header.page_type, PageType::InteriorTable);
+        assert!((1..=30).all(|i| db.table_infos.contains_key(&format!("t{i:02}"))));
+        // every table, index and view of test.db has a row in sqlite_schema
+        assert_eq!(db.num_cells, 49);
+        assert_eq!(
+            select(&db, "select name, value from t30"),
+            [[text("last"), Value::Real(0.5)]]
+        );
+    }
+
+    #[test]
+    fn uses_each_index_of_a_table() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        let mut idx_names = db
+            .table_idx_infos("people")
+            .iter()
+            .map(|idx_info| idx_info.idx_name.as_str())
+            .collect::<Vec<_>>();
+        idx_names.sort();
+        assert_eq!(idx_names, ["idx_people_age", "idx_people_country"]);
+
+        let sql = "select id, name from people where age = 30 order by id desc limit 3";
+        assert_eq!(idx_plan(&db, sql), Some(("idx_people_age".to_string(), 1)));
+        assert_eq!(
+            select(&db, sql),
+            [
+                [Value::Integer(462), text("p462")],
+                [Value::Integer(412), text("p412")],
+                [Value::Integer(362), text("p362")]
+            ]
+        );
+        let sql = "select count(*) from people where country = 'jp'";
+        assert_eq!(
+            idx_plan(&db, sql),
+            Some(("idx_people_country".to_string(), 1))
+        );
+        assert_eq!(select(&db, sql), [[Value::Integer(100)]]);
+    }
+
+    #[test]
+    fn uses_an_index_by_its_leading_column() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        // city is only the second column of idx_trips_country_city
+        let sql = "select count(*) from trips where city = 'c3'";
+        assert_eq!(idx_plan(&db, sql), None);
+        assert_eq!(select(&db, sql), [[Value::Integer(67)]]);
+
+        let sql = "select count(*) from trips where country = 'fr'";
+        assert_eq!(
+            idx_plan(&db, sql),
+            Some(("idx_trips_country_city".to_string(), 1))
+        );
+        assert_eq!(select(&db, sql), [[Value::Integer(100)]]);
+        let sql = "select id from trips where country = 'fr'";
+        assert_eq!(
+            idx_plan(&db, sql),
+            Some(("idx_trips_country_city".to_string(), 1))
+        );
+        let ids = select(&db, sql);
+        assert_eq!(ids.len(), 100);
+        assert!(ids
+            .iter()
+            .all(|id| matches!(id[..], [Value::Integer(id)] if id % 4 == 1)));
+    }
+
+    #[test]
+    fn seeks_a_prefix_of_a_composite_index() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        let sql =
+            "select id, year from trips where city = 'c3' and country = 'fr' order by id limit 4";
+        assert_eq!(
+            idx_plan(&db, sql),
+            Some(("idx_trips_country_city".to_string(), 2))
+        );
+        assert_eq!(
+            select(&db, sql),
+            [
+                [Value::Integer(9), Value::Integer(2009)],
+                [Value::Integer(21), Value::Integer(2001)],
+                [Value::Integer(33), Value::Integer(2013)],
+                [Value::Integer(45), Value::Integer(2005)]
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_rows_of_several_pages() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        let root_page_num = db.table_infos["people"].root_page_num;
+        let page_header = db.page_header(root_page_num).unwrap();
+        assert_eq!(page_header.page_type, PageType::InteriorTable);
+        assert_eq!(
+            select(&db, "select count(*) from people"),
+            [[Value::Integer(500)]]
+        );
+
+        // interior index pages hold entries too
+        let root_page_num = db.idx_infos["idx_people_age"].root_page_num;
+        let page_header = db.page_header(root_page_num).unwrap();
+        assert_eq!(page_header.page_type, PageType::InteriorIndex);
+        assert_eq!(BTreeCursor::count(&db, root_page_num).unwrap(), 500);
+    }
+
+    #[test]
+    fn keeps_nulls() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        // every tenth person has no age, and the id is the rowid, stored as NULL
+        assert_eq!(
+            select(&db, "select id, age from people where id = 10"),
+            [[Value::Integer(10), Value::Null]]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "select id, age from people where age is null order by id desc limit 2"
+            ),
+            [
+                [Value::Integer(500), Value::Null],
+                [Value::Integer(490), Value::Null]
+            ]
+        );
+        assert_eq!(
+            select(&db, "select count(*) from people where age is not null"),
+            [[Value::Integer(450)]]
+        );
+        assert_eq!(
+            select(&db, "select count(*) from people where age = null"),
+            [[Value::Integer(0)]]
+        );
+    }
+
+    #[test]
+    fn keeps_collations_and_partial_conditions() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        let idx_info = &db.idx_infos["idx_tags_name"];
+        assert_eq!(idx_info.columns[0].collation.as_deref(), Some("nocase"));
+        assert_eq!(idx_info.where_clause, None);
+        assert_eq!(
+            db.idx_infos["idx_tags_score"].where_clause,
+            Some(Expr::Binary {
+                op: BinaryOp::Ge,
+                lhs: Box::new(Expr::column("score")),
+                rhs: Box::new(Expr::Literal(Literal::Integer(50))),
+            })
+        );
+    }
+
+    #[test]
+    fn seeks_a_descending_nocase_primary_key() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        let table_info = &db.table_infos["events"];
+        let page_header = db.page_header(table_info.root_page_num).unwrap();
+        assert_eq!(page_header.page_type, PageType::InteriorIndex);
+        assert_eq!(
+            table_info
+                .primary_key
+                .iter()
+                .map(|c| (c.name.as_str(), c.descending, c.collation.as_deref()))
+                .collect::<Vec<_>>(),
+            [("kind", false, Some("nocase")), ("seq", true, None)]
+        );
+
+        // 'beta' sorts between 'Alpha' and 'GAMMA' only without regard to case
+        assert_eq!(
+            select(&db, "select seq from events where kind = 'beta' limit 3"),
+            [
+                [Value::Integer(298)],
+                [Value::Integer(295)],
+                [Value::Integer(292)]
+            ]
+        );
+        assert_eq!(
+            select(&db, "select count(*) from events where kind = 'GAMMA'"),
+            [[Value::Integer(100)]]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "select note from events where kind = 'beta' and seq = 100"
+            ),
+            [[text("e100")]]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "select seq from events where kind = 'Alpha' and seq < 10"
+            ),
+            [
+                [Value::Integer(9)],
+                [Value::Integer(6)],
+                [Value::Integer(3)]
+            ]
+        );
+    }
+
+    #[test]
+    fn numbers_automatic_indexes_like_sqlite() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        let idx_info = &db.idx_infos["sqlite_autoindex_codes_2"];
+        assert_eq!(idx_info.table_name, "codes");
+        assert_eq!(idx_info.columns[0].name, "label");
+        assert_eq!(db.table_idx_infos("codes").len(), 1);
+        assert_eq!(
+            select(&db, "select code from codes where label = 'sea'"),
+            [[text("c")]]
+        );
+    }
+
+    #[test]
+    fn skips_partial_indexes() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        let idx_names = db
+            .table_idx_infos("tags")
+            .iter()
+            .map(|idx_info| idx_info.idx_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(idx_names, ["idx_tags_name"]);
+
+        // idx_tags_score only has the scores of 50 and more
+        let sql = "select id from tags where score = 20";
+        assert_eq!(idx_plan(&db, sql), None);
+        assert_eq!(select(&db, sql), [[Value::Integer(5)]]);
+        assert_eq!(
+            select(&db, "select min(score), max(score) from tags"),
+            [[Value::Integer(10), Value::Integer(90)]]
+        );
+    }
+
+    #[test]
+    fn scans_ranges_of_an_index() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        let age = Some(("idx_people_age".to_string(), 0));
+        let sql = "select count(*) from people where age < 20";
+        assert_eq!(idx_plan(&db, sql), age);
+        assert_eq!(select(&db, sql), [[Value::Integer(10)]]);
+        let sql = "select count(*) from people where age > 65";
+        assert_eq!(idx_plan(&db, sql), age);
+        assert_eq!(select(&db, sql), [[Value::Integer(20)]]);
+        let sql = "select count(*) from people where age between 30 and 32";
+        assert_eq!(idx_plan(&db, sql), age);
+        assert_eq!(select(&db, sql), [[Value::Integer(30)]]);
+        // entries of equal age follow the rowid
+        assert_eq!(
+            select(
+                &db,
+                "select id, age from people where age between 30 and 31 limit 3"
+            ),
+            [
+                [Value::Integer(12), Value::Integer(30)],
+                [Value::Integer(62), Value::Integer(30)],
+                [Value::Integer(112), Value::Integer(30)]
+            ]
+        );
+
+        // LIKE ignores the case of ASCII letters
+        let country = Some(("idx_people_country".to_string(), 0));
+        let sql = "select count(*) from people where country like 'D%'";
+        assert_eq!(idx_plan(&db, sql), country);
+        assert_eq!(select(&db, sql), [[Value::Integer(100)]]);
+        let sql = "select id from people where country like 'j_' limit 2";
+        assert_eq!(idx_plan(&db, sql), country);
+        assert_eq!(select(&db, sql), [[Value::Integer(3)], [Value::Integer(8)]]);
+    }
+
+    #[test]
+    fn searches_only_binary_indexes() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        // idx_tags_name orders names without regard to case, but = and < compare
+        // them as BINARY
+        let sql = "select id from tags where name = 'apple'";
+        assert_eq!(idx_plan(&db, sql), None);
+        assert_eq!(select(&db, sql), [[Value::Integer(1)]]);
+        let sql = "select id from tags where name < 'b'";
+        assert_eq!(idx_plan(&db, sql), None);
+        assert_eq!(
+            select(&db, sql),
+            [
+                [Value::Integer(1)],
+                [Value::Integer(2)],
+                [Value::Integer(4)],
+                [Value::Integer(6)]
+            ]
+        );
+        assert_eq!(
+            select(&db, "select id from tags order by name limit 2"),
+            [[Value::Integer(4)], [Value::Integer(2)]]
+        );
+    }
+
+    #[test]
+    fn orders_by_the_primary_key() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        // the rows of a kind come by descending seq
+        let sql = "select seq from events where kind = 'beta' order by seq desc limit 3";
+        assert_eq!(order_plan(&db, sql), Some(false));
+        assert_eq!(
+            select(&db, sql),
+            [
+                [Value::Integer(298)],
+                [Value::Integer(295)],
+                [Value::Integer(292)]
+            ]
+        );
+        let sql = "select seq from events where kind = 'beta' order by seq limit 3";
+        assert_eq!(order_plan(&db, sql), Some(true));
+        assert_eq!(
+            select(&db, sql),
+            [
+                [Value::Integer(1)],
+                [Value::Integer(4)],
+                [Value::Integer(7)]
+            ]
+        );
+        // kind is NOCASE, but ORDER BY compares it as BINARY
+        assert_eq!(
+            order_plan(&db, "select seq from events order by kind, seq desc"),
+            None
+        );
+    }
+
+    #[test]
+    fn places_nulls_first_or_last() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        // NULLs come first in idx_people_age, either way of scanning it only puts
+        // them where they go by default
+        let sql = "select id from people where age > 60 order by age desc, id desc";
+        assert_eq!(order_plan(&db, sql), Some(true));
+        let sql = "select id from people where age > 60 order by age nulls last, id";
+        assert_eq!(order_plan(&db, sql), None);
+        let sql = "select id from people where age > 60 order by age desc nulls first, id desc";
+        assert_eq!(order_plan(&db, sql), None);
+
+        let rows = |sql| {
+            select(&db, sql)
+                .into_iter()
+                .map(|row| row[0].clone())
+                .collect::<Vec<_>>()
+        };
+        let ids = |ids: &[i64]| ids.iter().copied().map(Value::Integer).collect::<Vec<_>>();
+        assert_eq!(
+            rows("select id from people where id > 485 order by age nulls first, id limit 4"),
+            ids(&[490, 500, 486, 487])
+        );
+        assert_eq!(
+            rows("select id from people where id > 485 order by age desc nulls first, id limit 3"),
+            ids(&[490, 500, 499])
+        );
+        assert_eq!(
+            rows("select id from people where id > 485 order by age nulls last, id desc limit 3"),
+            ids(&[486, 487, 488])
+        );
+        assert_eq!(
+            rows("select id from people where id > 485 order by age desc, id desc limit 3"),
+            ids(&[499, 498, 497])
+        );
+    }
+
+    #[test]
+    fn stops_joining_at_the_limit() {
+        // the pages a full scan of people reads
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        select(&db, "select count(name) from people");
+        let scanned = db.page_cache.borrow().pages.len();
+
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        assert_eq!(
+            select(
+                &db,
+                "select p.name, t.year from people p join trips t on t.id = p.id limit 2"
+            ),
+            [
+                [text("p1"), Value::Integer(2001)],
+                [text("p2"), Value::Integer(2002)]
+            ]
+        );
+        let read = db.page_cache.borrow().pages.len();
+        assert!(read < scanned / 2, "{read} of {scanned} pages read");
+    }
+    #[test]
+    fn reads_joined_rows_as_they_are_taken() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        select(&db, "select count(name) from people");
+        let scanned = db.page_cache.borrow().pages.len();
+
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        let query = SelectQuery::from_query_string(
+            "select p.name, t.year from people p join trips t on t.id = p.id",
+        )
+        .unwrap();
+        let mut rows = db.execute_select(query).unwrap();
+        assert_eq!(
+            rows.next().unwrap().unwrap(),
+            [text("p1"), Value::Integer(2001)]
+        );
+        assert_eq!(
+            rows.next().unwrap().unwrap(),
+            [text("p2"), Value::Integer(2002)]
+        );
+        let read = db.page_cache.borrow().pages.len();
+        assert!(read < scanned / 2, "{read} of {scanned} pages read");
+        assert_eq!(rows.count(), 398);
+    }
+    #[test]
+    fn groups_by_result_columns() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        assert_eq!(
+            select(
+                &db,
+                "select age >= 60 as old, count(*) from people group by old order by 1"
+            ),
+            [
+                [Value::Null, Value::Integer(50)],
+                [Value::Integer(0), Value::Integer(370)],
+                [Value::Integer(1), Value::Integer(80)]
+            ]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "select age >= 60 as old, count(*) from people group by old having old"
+            ),
+            [[Value::Integer(1), Value::Integer(80)]]
+        );
+        // HAVING can name an aggregate by its alias, GROUP BY a column by its number
+        assert_eq!(
+            select(
+                &db,
+                "select country, count(*) as n from people where age < 25 group by 1 \
+                 having n > 15"
+            ),
+            [[text("de"), Value::Integer(20)]]
+        );
+        // a column of the table comes before a result column of the same name
+        assert_eq!(
+            select(
+                &db,
+                "select country as age, count(*) from people group by age order by 1 limit 2"
+            ),
+            [
+                [text("br"), Value::Integer(50)],
+                [text("br"), Value::Integer(10)]
+            ]
+        );
+
+        let sql = "select country, count(*) from people group by 3";
+        let err = SelectQuery::from_query_string(sql).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "1st GROUP BY term out of range - should be between 1 and 2"
+        );
+        for sql in [
+            "select country, count(*) from people group by 2",
+            "select country, count(*) as n from people group by n",
+        ] {
+            let query = SelectQuery::from_query_string(sql).unwrap();
+            let err = db.execute_select(query).err().unwrap();
+            assert_eq!(
+                err.to_string(),
+                "aggregate functions are not allowed in the GROUP BY clause"
+            );
+        }
+    }
+    #[test]
+    fn selects_from_views() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        assert_eq!(
+            select(
+                &db,
+                "select pname from young where pid > 400 order by pid desc limit 2"
+            ),
+            [[text("p456")], [text("p455")]]
+        );
+        assert_eq!(
+            select(&db, "select count(*) from young"),
+            [[Value::Integer(60)]]
+        );
+        // the outer WHERE holds for the groups of the view
+        assert_eq!(
+            select(
+                &db,
+                "select country, n from countries where n > 99 and country > 'f' \
+                 order by n, country desc"
+            ),
+            [
+                [text("us"), Value::Integer(100)],
+                [text("jp"), Value::Integer(100)],
+                [text("fr"), Value::Integer(100)]
+            ]
+        );
+        // the outer LIMIT applies to the rows the view's own LIMIT leaves
+        assert_eq!(
+            select(&db, "select year from first_trips limit 1 offset 1"),
+            [[Value::Integer(2002)]]
+        );
+
+        for (sql, message) in [
+            (
+                "select count(*) from countries",
+                "unsupported: aggregating the rows of view countries, which has aggregates",
+            ),
+            (
+                "select count(*) from first_trips",
+                "unsupported: aggregating the rows of view first_trips, which has a LIMIT",
+            ),
+            (
+                "select id from first_trips where year > 2001",
+                "unsupported: filtering or sorting the rows of view first_trips, which has a LIMIT",
+            ),
+            (
+                "select p.name from people p join young y on y.pid = p.id",
+                "unsupported: joining view young",
+            ),
+        ] {
+            let query = SelectQuery::from_query_string(sql).unwrap();
+            let err = db.execute_select(query).err().unwrap();
+            assert_eq!(err.to_string(), message);
+        }
+    }
+    #[test]
+    fn joins_tables() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        assert_eq!(
+            select(
+                &db,
+                "select people.name, trips.city from people inner join trips \
+                 on trips.id = people.id where trips.country = 'fr' and people.age > 60 \
+                 order by people.id limit 3"
+            ),
+            [
+                [text("p45"), text("c3")],
+                [text("p49"), text("c1")],
+                [text("p93"), text("c3")]
+            ]
+        );
+        // people without a trip get NULL for its columns
+        assert_eq!(
+            select(
+                &db,
+                "select p.id, t.year from people p left join trips t on t.id = p.id \
+                 where p.id >= 399 and p.id <= 402"
+            ),
+            [
+                [Value::Integer(399), Value::Integer(2019)],
+                [Value::Integer(400), Value::Integer(2000)],
+                [Value::Integer(401), Value::Null],
+                [Value::Integer(402), Value::Null]
+            ]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "select count(*) from people p left join trips t on t.id = p.id \
+                 where t.id is null"
+            ),
+            [[Value::Integer(100)]]
+        );
+        // ON conditions of a LEFT JOIN only decide which rows are NULL-extended
+        assert_eq!(
+            select(
+                &db,
+                "select p.id, t.id from people p left join trips t \
+                 on t.id = p.id and t.year > 2018 where p.id < 42 and p.id > 37"
+            ),
+            [
+                [Value::Integer(38), Value::Null],
+                [Value::Integer(39), Value::Integer(39)],
+                [Value::Integer(40), Value::Null],
+                [Value::Integer(41), Value::Null]
+            ]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "select t.name, c.code from tags t cross join codes c where t.score > 80 \
+                 order by c.code"
+            ),
+            [
+                [text("Date"), text("a")],
+                [text("Date"), text("b")],
+                [text("Date"), text("c")]
+            ]
+        );
+    }
+    #[test]
+    fn seeks_rowids() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        let sql = "select name from people where id = 42";
+        assert_eq!(rowid_plan(&db, sql), Some(42..=42));
+        assert_eq!(select(&db, sql), [[text("p42")]]);
+        let sql = "select id from people where id < 3";
+        assert_eq!(rowid_plan(&db, sql), Some(i64::MIN..=2));
+        assert_eq!(select(&db, sql), [[Value::Integer(1)], [Value::Integer(2)]]);
+        let sql = "select id from people where 498 < rowid";
+        assert_eq!(rowid_plan(&db, sql), Some(499..=i64::MAX));
+        assert_eq!(
+            select(&db, sql),
+            [[Value::Integer(499)], [Value::Integer(500)]]
+        );
+        let sql = "select id from people where id > 2.5 and id < 4";
+        assert_eq!(rowid_plan(&db, sql), Some(3..=3));
+        assert_eq!(select(&db, sql), [[Value::Integer(3)]]);
+
+        // a single rowid beats an index seek, an index seek a range of rowids
+        let sql = "select id from people where age = 30 and id = 12";
+        assert_eq!(rowid_plan(&db, sql), Some(12..=12));
+        assert_eq!(select(&db, sql), [[Value::Integer(12)]]);
+        let sql = "select id from people where age = 30 and id > 450";
+        assert_eq!(rowid_plan(&db, sql), None);
+        assert_eq!(idx_plan(&db, sql), Some(("idx_people_age".to_string(), 1)));
+        assert_eq!(select(&db, sql), [[Value::Integer(462)]]);
+        let sql = "select id from people where age > 30 and id < 3";
+        assert_eq!(rowid_plan(&db, sql), Some(i64::MIN..=2));
+    }
+    #[test]
+    fn rejects_unknown_columns_without_reading_rows() {
+        let db = Db::new(File::open("test.db").unwrap()).unwrap();
+        // no rowid is below 0, so no row is there to evaluate the columns on
+        for (sql, message) in [
+            (
+                "select id from people where id < 0 and bogus = 1",
+                "no such column: bogus",
+            ),
+            (
+                "select bogus from people where id < 0",
+                "no such column: bogus",
+            ),
+            (
+                "select id from people where id < 0 order by bogus",
+                "no such column: bogus",
+            ),
+            (
+                "select count(*) from people where id < 0 group by bogus",
+                "no such column: bogus",
+            ),
+            (
+                "select p.id from people p join trips t on t.bogus = p.id where p.id < 0",
+                "no such column: t.bogus",
+            ),
+        ] {
+            let query = SelectQuery::from_query_string(sql).unwrap();
+            let err = db.execute_select(query).err().unwrap();
+            assert_eq!(err.to_string(), message, "{sql}");
+        }
+    }
 }
@@ -0,0 +1,344 @@
+use std::cmp::Ordering;
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    ast::{BinaryOp, Expr, Literal},
+    page::Column,
+    query::Affinity,
+    util,
+    value::Value,
+};
+
+/// The values an expression is evaluated on.
+pub trait Row {
+    /// Returns the value and the affinity of a column by its name and the table name
+    /// or alias it's qualified with.
+    fn column(&self, table: Option<&str>, name: &str) -> Option<(Column, Affinity)>;
+
+    /// Returns the result of an aggregate function call, which only a group of rows has.
+    fn aggregate(&self, _call: &Expr) -> Option<Column> {
+        None
+    }
+}
+
+/// A row of a single table, which any qualifier refers to.
+impl<F: Fn(&str) -> Option<(Column, Affinity)>> Row for F {
+    fn column(&self, _table: Option<&str>, name: &str) -> Option<(Column, Affinity)> {
+        self(name)
+    }
+}
+
+/// The aggregate functions, none of which are also scalar functions.
+pub const AGGREGATES: [&str; 8] = [
+    "count",
+    "sum",
+    "total",
+    "avg",
+    "min",
+    "max",
+    "group_concat",
+    "string_agg",
+];
+
+/// Evaluates `expr` for one row. Boolean results are 1, 0 or NULL like in SQLite.
+pub fn eval<R: Row + ?Sized>(expr: &Expr, row: &R) -> Result<Column> {
+    eval_operand(expr, row).map(|(value, _)| value)
+}
+
+/// Returns true if a WHERE clause evaluating to `value` keeps the row.
+pub fn is_true(value: &Column) -> bool {
+    truth(value) == Some(true)
+}
+
+/// Converts a literal from a query into a value.
+pub fn literal_value(literal: &Literal) -> Column {
+    match literal {
+        Literal::Null => Column::Null,
+        Literal::Integer(i) => Column::I64(*i),
+        Literal::Real(r) => Column::F64(*r),
+        Literal::Text(s) => Column::Str(s.to_string()),
+        Literal::Blob(b) => Column::Blob(b.clone()),
+    }
+}
+
+/// Converts a value to a number the way arithmetic does: text and blobs count by their
+/// leading number, NULL stays NULL.
+pub fn numeric_value(value: Column) -> Column {
+    match value {
+        Column::Str(s) => util::parse_numeric(&s).unwrap_or(Column::F64(leading_number(&s))),
+        Column::Blob(b) => Column::F64(leading_number(&String::from_utf8_lossy(&b))),
+        c => c,
+    }
+}
+
+/// Returns true if `text` matches a LIKE pattern, where % matches any run of characters
+/// and _ any single one. ASCII letters match in either case.
+pub fn like(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    let (mut p, mut t) = (0, 0);
+    // the last % seen and the text position it matches up to, to backtrack to
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('%') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '_' || c.eq_ignore_ascii_case(&text[t]) => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((percent, end)) => {
+                    backtrack = Some((percent, end + 1));
+                    p = percent + 1;
+                    t = end + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '%')
+}
+
+/// Evaluates an expression to its value and affinity. Only column references have an
+/// affinity, the result of any other expression has none.
+fn eval_operand<R: Row + ?Sized>(expr: &Expr, row: &R) -> Result<(Column, Option<Affinity>)> {
+    let value = match expr {
+        Expr::Column { table, name } => {
+            let (value, affinity) = row.column(table.as_deref(), name).ok_or(match table {
+                Some(table) => anyhow!("no such column: {table}.{name}"),
+                None => anyhow!("no such column: {name}"),
+            })?;
+            return Ok((value, Some(affinity)));
+        }
+        Expr::Function { name, .. } => match row.aggregate(expr) {
+            Some(value) => value,
+            None if AGGREGATES.contains(&name.as_str()) => {
+                return Err(anyhow!("misuse of aggregate: {name}()"))
+            }
+            None => return Err(anyhow!("no such function: {name}")),
+        },
+        Expr::Literal(literal) => literal_value(literal),
+        Expr::Not(expr) => from_truth(truth(&eval(expr, row)?).map(|t| !t)),
+        Expr::Binary {
+            op: BinaryOp::And,
+            lhs,
+            rhs,
+        } => {
+            let lhs = truth(&eval(lhs, row)?);
+            if lhs == Some(false) {
+                return Ok((from_truth(lhs), None));
+            }
+            from_truth(match (lhs, truth(&eval(rhs, row)?)) {
+                (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            })
+        }
+        Expr::Binary {
+            op: BinaryOp::Or,
+            lhs,
+            rhs,
+        } => {
+            let lhs = truth(&eval(lhs, row)?);
+            if lhs == Some(true) {
+                return Ok((from_truth(lhs), None));
+            }
+            from_truth(match (lhs, truth(&eval(rhs, row)?)) {
+                (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            })
+        }
+        Expr::Binary {
+            op: BinaryOp::Like,
+            lhs,
+            rhs,
+        } => match (eval(lhs, row)?, eval(rhs, row)?) {
+            (Column::Null, _) | (_, Column::Null) => Column::Null,
+            (text, pattern) => {
+                let (text, pattern) = (Value::from(text), Value::from(pattern));
+                from_truth(Some(like(&pattern.to_string(), &text.to_string())))
+            }
+        },
+        Expr::Binary {
+            op: op @ (BinaryOp::Is | BinaryOp::IsNot),
+            lhs,
+            rhs,
+        } => {
+            let (lhs, rhs) = (eval_operand(lhs, row)?, eval_operand(rhs, row)?);
+            let is = match (&lhs.0, &rhs.0) {
+                (Column::Null, Column::Null) => true,
+                (Column::Null, _) | (_, Column::Null) => false,
+                _ => compare_operands(lhs, rhs) == Some(Ordering::Equal),
+            };
+            from_truth(Some(is == (*op == BinaryOp::Is)))
+        }
+        Expr::Binary { op, lhs, rhs } => {
+            let ordering = compare_operands(eval_operand(lhs, row)?, eval_operand(rhs, row)?);
+            from_truth(ordering.map(|o| match op {
+                BinaryOp::Eq => o.is_eq(),
+                BinaryOp::Ne => o.is_ne(),
+                BinaryOp::Lt => o.is_lt(),
+                BinaryOp::Le => o.is_le(),
+                BinaryOp::Gt => o.is_gt(),
+                BinaryOp::Ge => o.is_ge(),
+                BinaryOp::Like | BinaryOp::Is | BinaryOp::IsNot | BinaryOp::And | BinaryOp::Or => {
+                    unreachable!()
+                }
+            }))
+        }
+    };
+
+    Ok((value, None))
+}
+
+/// Compares two operands after converting them according to SQLite's rules: a numeric
+/// column turns the other side into a number if it can, a text column turns an
+/// operand without affinity into text. Comparisons with NULL have no result.
+fn compare_operands(
+    (lhs, lhs_affinity): (Column, Option<Affinity>),
+    (rhs, rhs_affinity): (Column, Option<Affinity>),
+) -> Option<Ordering> {
+    if lhs == Column::Null || rhs == Column::Null {
+        return None;
+    }
+
+    let is_numeric = |a: Option<Affinity>| {
+        matches!(
+            a,
+            Some(Affinity::Integer | Affinity::Real | Affinity::Numeric)
+        )
+    };
+    let (lhs, rhs) = match (lhs_affinity, rhs_affinity) {
+        (l, r) if is_numeric(l) && !is_numeric(r) => (lhs, Affinity::Numeric.apply(rhs)),
+        (l, r) if is_numeric(r) && !is_numeric(l) => (Affinity::Numeric.apply(lhs), rhs),
+        (Some(Affinity::Text), None) => (lhs, Affinity::Text.apply(rhs)),
+        (None, Some(Affinity::Text)) => (Affinity::Text.apply(lhs), rhs),
+        _ => (lhs, rhs),
+    };
+
+    Some(lhs.compare(&rhs))
+}
+
+/// Interprets a value as a boolean. Text and blobs count by their leading number,
+/// so 'abc' is false and '1abc' is true.
+fn truth(value: &Column) -> Option<bool> {
+    match value {
+        Column::Null => None,
+        Column::Str(s) => Some(leading_number(s) != 0.0),
+        Column::Blob(b) => Some(leading_number(&String::from_utf8_lossy(b)) != 0.0),
+        c => Some(c.as_f64().unwrap() != 0.0),
+    }
+}
+
+fn from_truth(truth: Option<bool>) -> Column {
+    match truth {
+        None => Column::Null,
+        Some(true) => Column::One,
+        Some(false) => Column::Zero,
+    }
+}
+
+/// Returns the value of the longest prefix of `s` that is a number, 0 if there is none.
+fn leading_number(s: &str) -> f64 {
+    let s = s.trim_start().as_bytes();
+    let digits = |mut i: usize| {
+        while s.get(i).is_some_and(u8::is_ascii_digit) {
+            i += 1;
+        }
+        i
+    };
+
+    let mut end = digits(usize::from(matches!(s.first(), Some(b'+' | b'-'))));
+    if s.get(end) == Some(&b'.') {
+        end = digits(end + 1);
+    }
+    if matches!(s.get(end), Some(b'e' | b'E')) {
+        let exp_start = end + 1 + usize::from(matches!(s.get(end + 1), Some(b'+' | b'-')));
+        let exp_end = digits(exp_start);
+        if exp_end > exp_start {
+            end = exp_end;
+        }
+    }
+
+    std::str::from_utf8(&s[..end])
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::Statement, parser};
+
+    fn where_clause(condition: &str) -> Expr {
+        match parser::parse(&format!("select a from t where {condition}")) {
+            Ok(Statement::Select(select)) => select.where_clause.unwrap(),
+            r => panic!("{r:?}"),
+        }
+    }
+
+    fn row(name: &str) -> Option<(Column, Affinity)> {
+        match name {
+            "n" => Some((Column::I8(10), Affinity::Integer)),
+            "t" => Some((Column::Str("10".to_string()), Affinity::Text)),
+            "b" => Some((Column::Str("10".to_string()), Affinity::Blob)),
+            "x" => Some((Column::Null, Affinity::Integer)),
+            _ => None,
+        }
+    }
+
+    fn check(condition: &str) -> Column {
+        eval(&where_clause(condition), &row).unwrap()
+    }
+
+    #[test]
+    fn it_works() {
+        assert_eq!(check("n > 9 AND n <= 10"), Column::One);
+        assert_eq!(check("n != 10 OR NOT (n < 5)"), Column::One);
+        assert_eq!(check("n = '10'"), Column::One);
+        assert_eq!(check("t = 10"), Column::One);
+        // a column without affinity compares as stored, text is greater than numbers
+        assert_eq!(check("b = 10"), Column::Zero);
+        assert_eq!(check("b > 10"), Column::One);
+        assert_eq!(check("'abc' > 5"), Column::One);
+        assert!(eval(&where_clause("missing = 1"), &row).is_err());
+    }
+
+    #[test]
+    fn handles_null() {
+        assert_eq!(check("x = 1"), Column::Null);
+        assert_eq!(check("NOT x = 1"), Column::Null);
+        assert_eq!(check("x = 1 AND n = 9"), Column::Zero);
+        assert_eq!(check("x = 1 OR n = 10"), Column::One);
+        assert!(!is_true(&check("x = 1 OR n = 9")));
+        assert_eq!(check("x BETWEEN 1 AND 20"), Column::Null);
+        assert_eq!(check("x LIKE '%'"), Column::Null);
+        assert_eq!(check("x = NULL"), Column::Null);
+        assert_eq!(check("x IS NULL"), Column::One);
+        assert_eq!(check("x ISNULL AND n NOTNULL"), Column::One);
+        assert_eq!(check("n IS NOT NULL"), Column::One);
+        assert_eq!(check("n NOT NULL"), Column::One);
+        assert_eq!(check("x IS 1"), Column::Zero);
+        assert_eq!(check("n IS '10'"), Column::One);
+    }
+
+    #[test]
+    fn matches_like_patterns() {
+        assert!(like("abc", "ABC"));
+        assert!(like("a%c", "abbbc"));
+        assert!(like("a_c", "abc"));
+        assert!(like("%b%", "abc"));
+        assert!(like("a%%", "a"));
+        assert!(!like("a_c", "ac"));
+        assert!(!like("a%d", "abcdx"));
+        assert_eq!(check("n BETWEEN 9 AND 10"), Column::One);
+        assert_eq!(check("t NOT LIKE '1%'"), Column::Zero);
+        assert_eq!(check("n LIKE '1_'"), Column::One);
+    }
+}
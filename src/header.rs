@@ -0,0 +1,325 @@
+use anyhow::{anyhow, Result};
+
+const MAGIC: &[u8; 16] = b"SQLite format 3\0";
+
+/// The 100-byte header at the start of the database file.
+#[derive(Debug)]
+pub struct DbHeader {
+    pub page_size: u32,
+    #[allow(dead_code)]
+    pub write_version: u8,
+    #[allow(dead_code)]
+    pub read_version: u8,
+    pub reserved_bytes: u8,
+    pub max_payload_fraction: u8,
+    pub min_payload_fraction: u8,
+    #[allow(dead_code)]
+    pub leaf_payload_fraction: u8,
+    pub file_change_counter: u32,
+    pub page_count: u32, // in-header database size, valid only if version_valid_for matches
+    #[allow(dead_code)]
+    pub first_freelist_trunk: u32,
+    #[allow(dead_code)]
+    pub freelist_count: u32,
+    #[allow(dead_code)]
+    pub schema_cookie: u32,
+    #[allow(dead_code)]
+    pub schema_format: u32,
+    #[allow(dead_code)]
+    pub default_cache_size: i32,
+    #[allow(dead_code)]
+    pub auto_vacuum_root: u32, // largest root b-tree page in auto-vacuum mode, 0 otherwise
+    pub text_encoding: TextEncoding,
+    #[allow(dead_code)]
+    pub user_version: i32,
+    #[allow(dead_code)]
+    pub incremental_vacuum: bool,
+    #[allow(dead_code)]
+    pub application_id: i32,
+    pub version_valid_for: u32,
+    #[allow(dead_code)]
+    pub sqlite_version_number: u32,
+}
+
+impl DbHeader {
+    pub fn from_bytes(bytes: &[u8; 100]) -> Result<Self> {
+        let u32_at = |offset: usize| {
+            u32::from_be_bytes([
+                bytes[offset],
+                bytes[offset + 1],
+                bytes[offset + 2],
+                bytes[offset + 3],
+            ])
+        };
+
+        if &bytes[0..16] != MAGIC {
+            return Err(anyhow!(
+                "file is not a database: bad magic string {:?}",
+                String::from_utf8_lossy(&bytes[0..16])
+            ));
+        }
+
+        // 1 stands for 65536 which doesn't fit into two bytes
+        let page_size = match u16::from_be_bytes([bytes[16], bytes[17]]) {
+            1 => 65536,
+            s if s >= 512 && s.is_power_of_two() => s as u32,
+            s => {
+                return Err(anyhow!(
+                    "page size {s} is not a power of two between 512 and 65536"
+                ))
+            }
+        };
+
+        // only a read version newer than WAL (2) makes the file unreadable, a newer
+        // write version just makes it read-only
+        let (write_version, read_version) = (bytes[18], bytes[19]);
+        if read_version > 2 {
+            return Err(anyhow!(
+                "unsupported file format read version {read_version}"
+            ));
+        }
+
+        let reserved_bytes = bytes[20];
+        if page_size - (reserved_bytes as u32) < 480 {
+            return Err(anyhow!(
+                "{reserved_bytes} reserved bytes leave less than 480 usable bytes in {page_size}-byte pages"
+            ));
+        }
+
+        let (max_payload_fraction, min_payload_fraction, leaf_payload_fraction) =
+            (bytes[21], bytes[22], bytes[23]);
+        if (
+            max_payload_fraction,
+            min_payload_fraction,
+            leaf_payload_fraction,
+        ) != (64, 32, 32)
+        {
+            return Err(anyhow!(
+                "payload fractions must be 64, 32 and 32, got {max_payload_fraction}, {min_payload_fraction} and {leaf_payload_fraction}"
+            ));
+        }
+
+        let file_change_counter = u32_at(24);
+        let page_count = u32_at(28);
+        let first_freelist_trunk = u32_at(32);
+        let freelist_count = u32_at(36);
+        if (first_freelist_trunk == 0) != (freelist_count == 0) {
+            return Err(anyhow!(
+                "inconsistent freelist: first trunk page {first_freelist_trunk}, {freelist_count} pages"
+            ));
+        }
+
+        let schema_cookie = u32_at(40);
+        let schema_format = u32_at(44);
+        if schema_format > 4 {
+            return Err(anyhow!("unsupported schema format {schema_format}"));
+        }
+
+        let default_cache_size = u32_at(48) as i32;
+        let auto_vacuum_root = u32_at(52);
+        let text_encoding = TextEncoding::try_from(u32_at(56))?;
+        let user_version = u32_at(60) as i32;
+        let incremental_vacuum = u32_at(64) != 0;
+        if incremental_vacuum && auto_vacuum_root == 0 {
+            return Err(anyhow!(
+                "incremental vacuum is enabled but auto-vacuum root page is 0"
+            ));
+        }
+        let application_id = u32_at(68) as i32;
+
+        let version_valid_for = u32_at(92);
+        let sqlite_version_number = u32_at(96);
+
+        let header = DbHeader {
+            page_size,
+            write_version,
+            read_version,
+            reserved_bytes,
+            max_payload_fraction,
+            min_payload_fraction,
+            leaf_payload_fraction,
+            file_change_counter,
+            page_count,
+            first_freelist_trunk,
+            freelist_count,
+            schema_cookie,
+            schema_format,
+            default_cache_size,
+            auto_vacuum_root,
+            text_encoding,
+            user_version,
+            incremental_vacuum,
+            application_id,
+            version_valid_for,
+            sqlite_version_number,
+        };
+
+        if let Some(page_count) = header.valid_page_count() {
+            if freelist_count >= page_count || first_freelist_trunk > page_count {
+                return Err(anyhow!(
+                    "freelist (trunk page {first_freelist_trunk}, {freelist_count} pages) doesn't fit into {page_count} pages"
+                ));
+            }
+        }
+
+        Ok(header)
+    }
+
+    /// The in-header database size. It's only trusted when the header was last
+    /// written by a version of SQLite that maintains it.
+    pub fn valid_page_count(&self) -> Option<u32> {
+        if self.page_count != 0 && self.version_valid_for == self.file_change_counter {
+            Some(self.page_count)
+        } else {
+            None
+        }
+    }
+
+    /// Checks the in-header database size against the actual size of the file.
+    pub fn check_file_size(&self, file_size: u64) -> Result<()> {
+        if let Some(page_count) = self.valid_page_count() {
+            let expected = page_count as u64 * self.page_size as u64;
+            if expected > file_size {
+                return Err(anyhow!(
+                    "header says the database has {page_count} pages ({expected} bytes), but the file is {file_size} bytes"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of bytes of a page that can hold b-tree data, i.e. everything but
+    /// the space reserved for extensions at the end of each page.
+    pub fn usable_size(&self) -> u64 {
+        self.page_size as u64 - self.reserved_bytes as u64
+    }
+
+    /// Largest payload that is stored entirely on a table leaf page.
+    pub fn max_local_table_leaf(&self) -> u64 {
+        self.usable_size() - 35
+    }
+
+    /// Largest payload that is stored entirely on an index page.
+    pub fn max_local_idx(&self) -> u64 {
+        (self.usable_size() - 12) * self.max_payload_fraction as u64 / 255 - 23
+    }
+
+    /// Smallest part of a spilled payload that is kept on the b-tree page.
+    pub fn min_local(&self) -> u64 {
+        (self.usable_size() - 12) * self.min_payload_fraction as u64 / 255 - 23
+    }
+}
+
+/// Encoding of all TEXT values in the database, including the schema SQL.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl TryFrom<u32> for TextEncoding {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u32) -> Result<Self> {
+        match value {
+            1 => Ok(TextEncoding::Utf8),
+            2 => Ok(TextEncoding::Utf16Le),
+            3 => Ok(TextEncoding::Utf16Be),
+            _ => Err(anyhow!("unknown text encoding {value} in db header")),
+        }
+    }
+}
+
+impl TextEncoding {
+    pub fn decode(&self, bytes: &[u8]) -> Result<String> {
+        match self {
+            TextEncoding::Utf8 => {
+                String::from_utf8(bytes.to_vec()).map_err(|e| anyhow!("invalid UTF-8 text: {e}"))
+            }
+            TextEncoding::Utf16Le | TextEncoding::Utf16Be => {
+                if bytes.len() % 2 != 0 {
+                    return Err(anyhow!(
+                        "invalid UTF-16 text: odd number of bytes {}",
+                        bytes.len()
+                    ));
+                }
+                let units = bytes
+                    .chunks_exact(2)
+                    .map(|c| match self {
+                        TextEncoding::Utf16Le => u16::from_le_bytes([c[0], c[1]]),
+                        _ => u16::from_be_bytes([c[0], c[1]]),
+                    })
+                    .collect::<Vec<_>>();
+                String::from_utf16(&units).map_err(|e| anyhow!("invalid UTF-16 text: {e}"))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for TextEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextEncoding::Utf8 => write!(f, "1 (utf8)"),
+            TextEncoding::Utf16Le => write!(f, "2 (utf16le)"),
+            TextEncoding::Utf16Be => write!(f, "3 (utf16be)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_header() -> [u8; 100] {
+        let mut bytes = [0_u8; 100];
+        bytes[0..16].copy_from_slice(MAGIC);
+        bytes[16..18].copy_from_slice(&4096_u16.to_be_bytes());
+        bytes[18] = 1;
+        bytes[19] = 1;
+        bytes[21] = 64;
+        bytes[22] = 32;
+        bytes[23] = 32;
+        bytes[24..28].copy_from_slice(&5_u32.to_be_bytes());
+        bytes[28..32].copy_from_slice(&4_u32.to_be_bytes());
+        bytes[44..48].copy_from_slice(&4_u32.to_be_bytes());
+        bytes[56..60].copy_from_slice(&2_u32.to_be_bytes());
+        bytes[92..96].copy_from_slice(&5_u32.to_be_bytes());
+        bytes[96..100].copy_from_slice(&3034000_u32.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn it_works() {
+        let header = DbHeader::from_bytes(&sample_header()).unwrap();
+        assert_eq!(header.page_size, 4096);
+        assert_eq!(header.text_encoding, TextEncoding::Utf16Le);
+        assert_eq!(header.valid_page_count(), Some(4));
+        assert_eq!(header.sqlite_version_number, 3034000);
+        assert!(header.check_file_size(4 * 4096).is_ok());
+        assert!(header.check_file_size(3 * 4096).is_err());
+
+        let mut bytes = sample_header();
+        bytes[16..18].copy_from_slice(&1_u16.to_be_bytes());
+        assert_eq!(DbHeader::from_bytes(&bytes).unwrap().page_size, 65536);
+
+        let mut bytes = sample_header();
+        bytes[0] = b's';
+        assert!(DbHeader::from_bytes(&bytes).is_err());
+
+        let mut bytes = sample_header();
+        bytes[16..18].copy_from_slice(&1000_u16.to_be_bytes());
+        assert!(DbHeader::from_bytes(&bytes).is_err());
+
+        let mut bytes = sample_header();
+        bytes[36..40].copy_from_slice(&3_u32.to_be_bytes());
+        assert!(DbHeader::from_bytes(&bytes).is_err());
+
+        let mut bytes = sample_header();
+        bytes[18] = 3;
+        bytes[80] = 1;
+        assert!(DbHeader::from_bytes(&bytes).is_ok());
+        bytes[19] = 3;
+        assert!(DbHeader::from_bytes(&bytes).is_err());
+    }
+}
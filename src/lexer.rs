@@ -0,0 +1,303 @@
+use crate::parser::ParseError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    // keywords are words too, the parser tells them apart
+    Word(String),
+    // "name", `name` or [name]
+    QuotedIdent(String),
+    Str(String),
+    Integer(i64),
+    Real(f64),
+    Blob(Vec<u8>),
+    LParen,
+    RParen,
+    Comma,
+    Semicolon,
+    Dot,
+    Star,
+    Plus,
+    Minus,
+    Slash,
+    Percent,
+    Concat,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    // byte offset of the token in the sql text
+    pub offset: usize,
+    pub len: usize,
+}
+
+impl Token {
+    /// Returns true if the token is the keyword `keyword`, written in any case.
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(&self.kind, TokenKind::Word(w) if w.eq_ignore_ascii_case(keyword))
+    }
+}
+
+/// Splits sql text into tokens, skipping whitespace and comments.
+pub fn tokenize(sql: &str) -> Result<Vec<Token>, ParseError> {
+    let bytes = sql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let start = i;
+        let c = bytes[i];
+
+        let kind = match c {
+            b' ' | b'\t' | b'\n' | b'\r' | b'\x0c' => {
+                i += 1;
+                continue;
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                // an unterminated comment runs to the end of the input
+                i = sql[i + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |end| i + 2 + end + 2);
+                continue;
+            }
+            b'\'' => {
+                let (s, end) = read_quoted(sql, i, '\'')?;
+                i = end;
+                TokenKind::Str(s)
+            }
+            b'"' | b'`' => {
+                let (s, end) = read_quoted(sql, i, c as char)?;
+                i = end;
+                TokenKind::QuotedIdent(s)
+            }
+            b'[' => {
+                let end = sql[i..]
+                    .find(']')
+                    .ok_or_else(|| unrecognized(sql, i, bytes.len()))?;
+                i += end + 1;
+                TokenKind::QuotedIdent(sql[start + 1..i - 1].to_string())
+            }
+            b'x' | b'X' if bytes.get(i + 1) == Some(&b'\'') => {
+                let (hex, end) = read_quoted(sql, i + 1, '\'')?;
+                i = end;
+                TokenKind::Blob(decode_hex(&hex).ok_or_else(|| unrecognized(sql, start, end))?)
+            }
+            b'0'..=b'9' => {
+                let (kind, end) = read_number(sql, i)?;
+                i = end;
+                kind
+            }
+            b'.' if bytes.get(i + 1).is_some_and(u8::is_ascii_digit) => {
+                let (kind, end) = read_number(sql, i)?;
+                i = end;
+                kind
+            }
+            c if c == b'_' || c.is_ascii_alphabetic() || c >= 0x80 => {
+                while i < bytes.len()
+                    && (bytes[i] == b'_'
+                        || bytes[i] == b'$'
+                        || bytes[i].is_ascii_alphanumeric()
+                        || bytes[i] >= 0x80)
+                {
+                    i += 1;
+                }
+                TokenKind::Word(sql[start..i].to_string())
+            }
+            _ => {
+                let next = bytes.get(i + 1).copied();
+                let (kind, len) = match (c, next) {
+                    (b'(', _) => (TokenKind::LParen, 1),
+                    (b')', _) => (TokenKind::RParen, 1),
+                    (b',', _) => (TokenKind::Comma, 1),
+                    (b';', _) => (TokenKind::Semicolon, 1),
+                    (b'.', _) => (TokenKind::Dot, 1),
+                    (b'*', _) => (TokenKind::Star, 1),
+                    (b'+', _) => (TokenKind::Plus, 1),
+                    (b'-', _) => (TokenKind::Minus, 1),
+                    (b'/', _) => (TokenKind::Slash, 1),
+                    (b'%', _) => (TokenKind::Percent, 1),
+                    (b'|', Some(b'|')) => (TokenKind::Concat, 2),
+                    (b'=', Some(b'=')) => (TokenKind::Eq, 2),
+                    (b'=', _) => (TokenKind::Eq, 1),
+                    (b'!', Some(b'=')) => (TokenKind::Ne, 2),
+                    (b'<', Some(b'>')) => (TokenKind::Ne, 2),
+                    (b'<', Some(b'=')) => (TokenKind::Le, 2),
+                    (b'<', _) => (TokenKind::Lt, 1),
+                    (b'>', Some(b'=')) => (TokenKind::Ge, 2),
+                    (b'>', _) => (TokenKind::Gt, 1),
+                    _ => {
+                        let len = sql[i..].chars().next().map_or(1, char::len_utf8);
+                        return Err(unrecognized(sql, i, i + len));
+                    }
+                };
+                i += len;
+                kind
+            }
+        };
+
+        tokens.push(Token {
+            kind,
+            offset: start,
+            len: i - start,
+        });
+    }
+
+    Ok(tokens)
+}
+
+/// Reads text between `quote` characters starting at `start`, where a doubled quote
+/// stands for the quote itself. Returns the text and the offset after the closing quote.
+fn read_quoted(sql: &str, start: usize, quote: char) -> Result<(String, usize), ParseError> {
+    let mut s = String::new();
+    let mut chars = sql[start + 1..].char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == quote {
+            if chars.peek().map(|(_, c)| *c) == Some(quote) {
+                chars.next();
+            } else {
+                return Ok((s, start + 1 + i + 1));
+            }
+        }
+        s.push(c);
+    }
+
+    Err(unrecognized(sql, start, sql.len()))
+}
+
+/// Reads an integer, real or hexadecimal number starting at `start`.
+fn read_number(sql: &str, start: usize) -> Result<(TokenKind, usize), ParseError> {
+    let bytes = sql.as_bytes();
+    let mut i = start;
+
+    if sql[start..].starts_with("0x") || sql[start..].starts_with("0X") {
+        i += 2;
+        while i < bytes.len() && bytes[i].is_ascii_hexdigit() {
+            i += 1;
+        }
+        // hex literals are 64-bit two's complement
+        let value =
+            u64::from_str_radix(&sql[start + 2..i], 16).map_err(|_| unrecognized(sql, start, i))?;
+        return Ok((TokenKind::Integer(value as i64), i));
+    }
+
+    let mut is_real = false;
+    while i < bytes.len() && bytes[i].is_ascii_digit() {
+        i += 1;
+    }
+    if bytes.get(i) == Some(&b'.') {
+        is_real = true;
+        i += 1;
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+    }
+    if matches!(bytes.get(i), Some(b'e' | b'E')) {
+        let mut j = i + 1;
+        if matches!(bytes.get(j), Some(b'+' | b'-')) {
+            j += 1;
+        }
+        if bytes.get(j).is_some_and(u8::is_ascii_digit) {
+            is_real = true;
+            i = j;
+            while i < bytes.len() && bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+        }
+    }
+    // a number runs straight into a word, like 12abc
+    if bytes
+        .get(i)
+        .is_some_and(|c| *c == b'_' || c.is_ascii_alphabetic())
+    {
+        return Err(unrecognized(sql, start, i + 1));
+    }
+
+    let text = &sql[start..i];
+    let kind = match text.parse::<i64>() {
+        Ok(n) if !is_real => TokenKind::Integer(n),
+        // integers too big for 64 bits become reals
+        _ => TokenKind::Real(text.parse().map_err(|_| unrecognized(sql, start, i))?),
+    };
+
+    Ok((kind, i))
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn unrecognized(sql: &str, start: usize, end: usize) -> ParseError {
+    ParseError::UnrecognizedToken {
+        token: sql[start..end].to_string(),
+        offset: start,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(sql: &str) -> Vec<TokenKind> {
+        tokenize(sql).unwrap().into_iter().map(|t| t.kind).collect()
+    }
+
+    #[test]
+    fn it_works() {
+        assert_eq!(
+            kinds("select \"size range\", x'0aFF' -- comment\nFROM t where a >= 'it''s';"),
+            vec![
+                TokenKind::Word("select".to_string()),
+                TokenKind::QuotedIdent("size range".to_string()),
+                TokenKind::Comma,
+                TokenKind::Blob(vec![0x0a, 0xff]),
+                TokenKind::Word("FROM".to_string()),
+                TokenKind::Word("t".to_string()),
+                TokenKind::Word("where".to_string()),
+                TokenKind::Word("a".to_string()),
+                TokenKind::Ge,
+                TokenKind::Str("it's".to_string()),
+                TokenKind::Semicolon,
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_numbers() {
+        assert_eq!(
+            kinds("1 2.5 .5 1e3 0x10 9223372036854775808"),
+            vec![
+                TokenKind::Integer(1),
+                TokenKind::Real(2.5),
+                TokenKind::Real(0.5),
+                TokenKind::Real(1000.0),
+                TokenKind::Integer(16),
+                TokenKind::Real(9223372036854775808.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unterminated_strings() {
+        let err = tokenize("select 'abc").unwrap_err();
+        assert_eq!(err.to_string(), "unrecognized token: \"'abc\"");
+        assert_eq!(err.offset(), 7);
+    }
+}
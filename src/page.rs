@@ -1,9 +1,12 @@
 use anyhow::anyhow;
-use std::collections::{BTreeMap, HashSet};
-use std::fmt::Display;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
 
+use crate::ast::{Expr, IndexedColumn};
+use crate::header::TextEncoding;
+use crate::query::Affinity;
 use crate::util::{get_content_size_type, read_varint};
-use std::{fs::File, os::unix::fs::FileExt};
 
 #[derive(Debug, PartialEq)]
 pub enum PageType {
@@ -33,6 +36,7 @@ pub enum Page {
 
 #[derive(Debug)]
 pub struct LeafTablePage {
+    #[allow(dead_code)]
     pub page_header: PageHeader,
     //pub cell_pointer_array: Vec<u16>,
     pub cells: Vec<LeafTableCell>,
@@ -63,26 +67,86 @@ pub struct LeafIdxPage {
 
 #[derive(Debug)]
 pub struct TableInfo {
+    // the name as declared, the maps below hold column names in lowercase
+    pub table_name: String,
     pub root_page_num: u32,
     // column_name -> order
     pub column_orders: BTreeMap<String, usize>,
+    // column_name -> affinity
+    pub column_affinities: BTreeMap<String, Affinity>,
+    // rows are stored in an index b-tree keyed by the primary key
+    pub without_rowid: bool,
+    // primary key columns in key order, with their collating sequence
+    pub primary_key: Vec<IndexedColumn>,
+    // the INTEGER PRIMARY KEY column that holds the rowid
+    pub rowid_alias: Option<String>,
+}
+
+impl TableInfo {
+    /// Returns the value of a column as sqlite3 would show it. Columns with REAL
+    /// affinity may keep integral values as integers on disk, those are turned back
+    /// into floats.
+    pub fn column_value<'a>(&self, column_name: &str, column: &'a Column) -> Cow<'a, Column> {
+        match (self.column_affinities.get(column_name), column.as_i64()) {
+            (Some(Affinity::Real), Some(i)) => Cow::Owned(Column::F64(i as f64)),
+            _ => Cow::Borrowed(column),
+        }
+    }
+
+    /// Returns true if the column name refers to the rowid: the INTEGER PRIMARY KEY
+    /// column, or rowid, oid and _rowid_ unless a column has that name.
+    pub fn is_rowid(&self, column_name: &str) -> bool {
+        if self.rowid_alias.as_deref() == Some(column_name) {
+            return true;
+        }
+        !self.without_rowid
+            && !self.column_orders.contains_key(column_name)
+            && ROWID_NAMES
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(column_name))
+    }
 }
 
+/// The names that refer to the rowid of a table.
+pub const ROWID_NAMES: [&str; 3] = ["rowid", "oid", "_rowid_"];
+
 #[derive(Debug)]
 pub struct IdxInfo {
     pub root_page_num: u32,
-    #[allow(dead_code)]
     pub idx_name: String,
+    pub table_name: String,
+    // indexed columns in index order
+    pub columns: Vec<IndexedColumn>,
+    // the condition of a partial index, None if every row has an entry
+    pub where_clause: Option<Expr>,
+}
+
+#[derive(Debug)]
+pub struct ViewInfo {
+    pub view_name: String,
+    // explicit column names from CREATE VIEW v(a, b), otherwise those of the SELECT
+    pub columns: Option<Vec<String>>,
+    pub select_sql: String,
+}
+
+#[derive(Debug)]
+pub struct TriggerInfo {
+    #[allow(dead_code)]
+    pub trigger_name: String,
+    #[allow(dead_code)]
+    pub table_name: String,
     #[allow(dead_code)]
-    pub columns: HashSet<String>,
+    pub sql: String,
 }
 
 #[derive(Debug)]
 pub struct FirstPage {
     //pub db_header: DbHeader,
-    pub page: LeafTablePage,
+    pub cells: Vec<LeafTableCell>, // rows of sqlite_schema
     pub table_infos: BTreeMap<String, TableInfo>, // TableName->TableInfo
-    pub idx_infos: BTreeMap<String, IdxInfo>,     // TableName->IdxInfo
+    pub idx_infos: BTreeMap<String, IdxInfo>, // IdxName->IdxInfo
+    pub view_infos: BTreeMap<String, ViewInfo>, // ViewName->ViewInfo
+    pub trigger_infos: BTreeMap<String, TriggerInfo>, // TriggerName->TriggerInfo
 }
 
 #[derive(Debug)]
@@ -124,45 +188,58 @@ pub struct RecordHeader {
 }
 
 impl RecordHeader {
-    pub fn from_file(file: &File, pointer: u64) -> anyhow::Result<(Self, u64)> {
-        let mut buf_varint = [0_u8; 9];
-        let mut current_offset = 0;
-        // header_size:
-        file.read_exact_at(&mut buf_varint, pointer + current_offset)
-            .map_err(|e| anyhow!("can't read cell header size: {e} at offset {current_offset}"))?;
-        let (record_header_size, record_header_size_bytes) = read_varint(&buf_varint);
-        current_offset += record_header_size_bytes as u64;
+    /// Parses the record header at the start of an assembled cell payload.
+    /// Returns the header and the number of bytes it takes.
+    pub fn from_bytes(payload: &[u8]) -> anyhow::Result<(Self, usize)> {
+        let (record_header_size, record_header_size_bytes) = read_varint(varint_slice(payload, 0));
+        let record_header_size: usize = record_header_size
+            .try_into()
+            .map_err(|_| anyhow!("bad record header size {record_header_size}"))?;
+        if record_header_size > payload.len() {
+            return Err(anyhow!(
+                "record header size {record_header_size} exceeds payload size {}",
+                payload.len()
+            ));
+        }
+        let mut current_offset = record_header_size_bytes as usize;
 
         let mut column_types = Vec::new();
         // column types
-        let mut bytes_read = 0;
-        while bytes_read < record_header_size - record_header_size_bytes as i64 {
-            file.read_exact_at(&mut buf_varint, pointer + current_offset)?;
-            let (column_type, o) = read_varint(&buf_varint);
-            current_offset += o as u64;
-            bytes_read += o as i64;
+        while current_offset < record_header_size {
+            let (column_type, o) = read_varint(varint_slice(payload, current_offset));
+            current_offset += o as usize;
 
             column_types.push(column_type);
         }
         Ok((Self { column_types }, current_offset))
     }
 
-    pub fn read_columns(&self, file: &File, pointer: u64) -> anyhow::Result<(Vec<Column>, u64)> {
-        let mut current_offset = 0_u64;
+    /// Decodes the record body that starts at `body` according to the column types.
+    /// Returns the columns and the number of bytes consumed.
+    pub fn read_columns(
+        &self,
+        body: &[u8],
+        text_encoding: TextEncoding,
+    ) -> anyhow::Result<(Vec<Column>, usize)> {
+        let mut current_offset = 0_usize;
         let mut columns = Vec::new();
         for t in &self.column_types {
             // todo: tightly couple sizes and types
-            let (size, typ) = get_content_size_type(*t);
-            let mut buf: Vec<u8> = vec![0; size.try_into().unwrap()];
-            file.read_exact_at(buf.as_mut_slice(), pointer + current_offset)?;
+            let (size, typ) = get_content_size_type(*t)?;
+            let size = size as usize;
+            let buf = body
+                .get(current_offset..current_offset + size)
+                .ok_or(anyhow!(
+                    "column of {size} bytes at offset {current_offset} is out of record bounds"
+                ))?;
             current_offset += size;
             match typ {
                 ColumnType::Str => {
-                    let s = String::from_utf8(buf).unwrap();
+                    let s = text_encoding.decode(buf)?;
                     columns.push(Column::Str(s));
                 }
                 ColumnType::I8 => {
-                    let val = i8::from_be_bytes([buf.as_slice()[0]]);
+                    let val = i8::from_be_bytes([buf[0]]);
                     columns.push(Column::I8(val));
                 }
                 ColumnType::I16 => {
@@ -170,9 +247,31 @@ impl RecordHeader {
                     columns.push(Column::I16(val));
                 }
                 ColumnType::I24 => {
-                    let val = i32::from_be_bytes([0, buf[0], buf[1], buf[2]]);
+                    // shift back down to sign-extend the 24-bit value
+                    let val = i32::from_be_bytes([buf[0], buf[1], buf[2], 0]) >> 8;
                     columns.push(Column::I24(val));
                 }
+                ColumnType::I32 => {
+                    let val = i32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]);
+                    columns.push(Column::I32(val));
+                }
+                ColumnType::I48 => {
+                    let val =
+                        i64::from_be_bytes([buf[0], buf[1], buf[2], buf[3], buf[4], buf[5], 0, 0])
+                            >> 16;
+                    columns.push(Column::I48(val));
+                }
+                ColumnType::I64 => {
+                    let val = i64::from_be_bytes(buf.try_into().unwrap());
+                    columns.push(Column::I64(val));
+                }
+                ColumnType::F64 => {
+                    let val = f64::from_be_bytes(buf.try_into().unwrap());
+                    columns.push(Column::F64(val));
+                }
+                ColumnType::Blob => {
+                    columns.push(Column::Blob(buf.to_vec()));
+                }
                 ColumnType::Zero => {
                     columns.push(Column::Zero);
                 }
@@ -187,6 +286,13 @@ impl RecordHeader {
     }
 }
 
+/// Returns at most 9 bytes starting at `offset`, enough for one varint.
+pub fn varint_slice(bytes: &[u8], offset: usize) -> &[u8] {
+    let start = offset.min(bytes.len());
+    let end = (offset + 9).min(bytes.len());
+    &bytes[start..end]
+}
+
 #[derive(Debug)]
 pub struct RecordBody {
     pub columns: Vec<Column>,
@@ -196,7 +302,7 @@ pub struct RecordBody {
 pub struct InteriorIdxRecordBody {
     pub columns: Vec<Column>,
     #[allow(dead_code)]
-    pub rowid: i64,
+    pub rowid: Option<i64>,
 }
 
 #[derive(Debug)]
@@ -208,6 +314,11 @@ pub type Str = String;
 pub type I8 = i8;
 pub type I16 = i16;
 pub type I24 = i32;
+pub type I32 = i32;
+pub type I48 = i64;
+pub type I64 = i64;
+pub type F64 = f64;
+pub type Blob = Vec<u8>;
 
 #[derive(Debug)]
 pub enum ColumnType {
@@ -215,39 +326,88 @@ pub enum ColumnType {
     I8,
     I16,
     I24,
+    I32,
+    I48,
+    I64,
+    F64,
+    Blob,
     Zero,
     One,
     Null,
 }
 
-#[derive(Debug, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub enum Column {
     Null,
     Str(Str),
     I8(I8),
     I16(I16),
     I24(I24),
+    I32(I32),
+    I48(I48),
+    I64(I64),
+    F64(F64),
+    Blob(Blob),
     Zero,
     One,
 }
 
-impl Display for Column {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl Column {
+    pub fn as_f64(&self) -> Option<f64> {
         match self {
-            Column::Str(s) => write!(f, "{}", s),
-            Column::I8(i) => write!(f, "{}", i),
-            Column::I16(i) => write!(f, "{}", i),
-            Column::I24(i) => write!(f, "{}", i),
-            Column::Zero => write!(f, "0"),
-            Column::One => write!(f, "1"),
-            Column::Null => write!(f, "NULL"),
+            Column::F64(f) => Some(*f),
+            c => c.as_i64().map(|i| i as f64),
+        }
+    }
+
+    /// Compares two values the way SQLite orders them: NULL first, then numbers,
+    /// then text, then blobs. Text and blobs compare bytewise.
+    pub fn compare(&self, other: &Column) -> Ordering {
+        fn class(c: &Column) -> u8 {
+            match c {
+                Column::Null => 0,
+                Column::Str(_) => 2,
+                Column::Blob(_) => 3,
+                _ => 1,
+            }
+        }
+
+        match (self, other) {
+            (Column::Str(a), Column::Str(b)) => a.as_bytes().cmp(b.as_bytes()),
+            (Column::Blob(a), Column::Blob(b)) => a.cmp(b),
+            (a, b) if class(a) == 1 && class(b) == 1 => match (a.as_i64(), b.as_i64()) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                _ => a
+                    .as_f64()
+                    .unwrap()
+                    .partial_cmp(&b.as_f64().unwrap())
+                    .unwrap_or(Ordering::Equal),
+            },
+            (a, b) => class(a).cmp(&class(b)),
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Column::I8(i) => Some(*i as i64),
+            Column::I16(i) => Some(*i as i64),
+            Column::I24(i) => Some(*i as i64),
+            Column::I32(i) => Some(*i as i64),
+            Column::I48(i) => Some(*i),
+            Column::I64(i) => Some(*i),
+            Column::Zero => Some(0),
+            Column::One => Some(1),
+            _ => None,
         }
     }
 }
 
+/// Prints columns like sqlite3 does in list mode. With the alternate flag (`{:#}`)
+/// blobs are printed as `X'..'` hex literals, like in quote mode.
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::value::Value;
 
     #[test]
     fn it_works() {
@@ -258,4 +418,99 @@ mod tests {
         assert!(c1 > c2);
         assert!(c1 < c3);
     }
+
+    #[test]
+    fn reads_record_from_bytes() {
+        // header size 4, types: text of 3 bytes, i8, null
+        let payload = [0x04, 0x13, 0x01, 0x00, b'a', b'b', b'c', 0x2a];
+        let (header, o) = RecordHeader::from_bytes(&payload).unwrap();
+        assert_eq!(header.column_types, vec![0x13, 0x01, 0x00]);
+        assert_eq!(o, 4);
+
+        let (columns, n) = header
+            .read_columns(&payload[o..], TextEncoding::Utf8)
+            .unwrap();
+        assert_eq!(
+            columns,
+            vec![
+                Column::Str(String::from("abc")),
+                Column::I8(42),
+                Column::Null
+            ]
+        );
+        assert_eq!(n, 4);
+
+        assert!(header
+            .read_columns(&payload[o..o + 2], TextEncoding::Utf8)
+            .is_err());
+    }
+
+    #[test]
+    fn finds_rowid_columns() {
+        let table_info = TableInfo {
+            table_name: "t".to_string(),
+            root_page_num: 2,
+            column_orders: BTreeMap::from([("id".to_string(), 0), ("oid".to_string(), 1)]),
+            column_affinities: BTreeMap::new(),
+            without_rowid: false,
+            primary_key: vec![IndexedColumn {
+                name: "id".to_string(),
+                descending: false,
+                collation: None,
+            }],
+            rowid_alias: Some("id".to_string()),
+        };
+        assert!(table_info.is_rowid("id"));
+        assert!(table_info.is_rowid("ROWID"));
+        assert!(table_info.is_rowid("_rowid_"));
+        // a column of that name hides the rowid
+        assert!(!table_info.is_rowid("oid"));
+        assert!(!table_info.is_rowid("name"));
+    }
+
+    #[test]
+    fn reads_wide_integers_floats_and_blobs() {
+        // types: i24, i48, i64, f64, blob of 2 bytes
+        let mut payload = vec![0x06, 0x03, 0x05, 0x06, 0x07, 0x10];
+        payload.extend_from_slice(&[0xff, 0xff, 0xfe]);
+        payload.extend_from_slice(&[0x00, 0x01, 0x00, 0x00, 0x00, 0x00]);
+        payload.extend_from_slice(&i64::MIN.to_be_bytes());
+        payload.extend_from_slice(&1.5_f64.to_be_bytes());
+        payload.extend_from_slice(&[0x41, 0xff]);
+
+        let (header, o) = RecordHeader::from_bytes(&payload).unwrap();
+        let (columns, _) = header
+            .read_columns(&payload[o..], TextEncoding::Utf8)
+            .unwrap();
+        assert_eq!(
+            columns,
+            vec![
+                Column::I24(-2),
+                Column::I48(1 << 32),
+                Column::I64(i64::MIN),
+                Column::F64(1.5),
+                Column::Blob(vec![0x41, 0xff]),
+            ]
+        );
+        assert_eq!(Value::from(columns[3].clone()).to_string(), "1.5");
+        assert_eq!(format!("{:#}", Value::from(columns[4].clone())), "X'41ff'");
+    }
+
+    #[test]
+    fn decodes_text_in_db_encoding() {
+        // text of 4 bytes, "hé" in UTF-16
+        let le = [0x02, 0x15, b'h', 0x00, 0xe9, 0x00];
+        let be = [0x02, 0x15, 0x00, b'h', 0x00, 0xe9];
+        for (payload, encoding) in [(le, TextEncoding::Utf16Le), (be, TextEncoding::Utf16Be)] {
+            let (header, o) = RecordHeader::from_bytes(&payload).unwrap();
+            let (columns, _) = header.read_columns(&payload[o..], encoding).unwrap();
+            assert_eq!(columns, vec![Column::Str(String::from("hé"))]);
+        }
+
+        let invalid = [0x02, 0x0f, 0xff];
+        let (header, o) = RecordHeader::from_bytes(&invalid).unwrap();
+        assert!(header
+            .read_columns(&invalid[o..], TextEncoding::Utf8)
+            .is_err());
+    }
 }
@@ -0,0 +1,1023 @@
+use thiserror::Error;
+
+use crate::{
+    ast::{
+        BinaryOp, ColumnConstraint, ColumnDef, CreateIndex, CreateTable, CreateView, Expr,
+        IndexedColumn, Join, JoinKind, Literal, NullsOrder, OrderingTerm, ResultColumn, Select,
+        Statement, TableConstraint, TableRef,
+    },
+    lexer::{tokenize, Token, TokenKind},
+};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ParseError {
+    #[error("near \"{near}\": syntax error")]
+    Syntax { near: String, offset: usize },
+    #[error("incomplete input")]
+    Incomplete { offset: usize },
+    #[error("unrecognized token: \"{token}\"")]
+    UnrecognizedToken { token: String, offset: usize },
+}
+
+impl ParseError {
+    /// Returns the byte offset in the sql text where the error was found.
+    pub fn offset(&self) -> usize {
+        match self {
+            ParseError::Syntax { offset, .. }
+            | ParseError::Incomplete { offset }
+            | ParseError::UnrecognizedToken { offset, .. } => *offset,
+        }
+    }
+}
+
+// keywords that can't be used as unquoted names
+const RESERVED: [&str; 47] = [
+    "ALL",
+    "AND",
+    "AS",
+    "BETWEEN",
+    "BY",
+    "CASE",
+    "CHECK",
+    "COLLATE",
+    "CONSTRAINT",
+    "CREATE",
+    "CROSS",
+    "DEFAULT",
+    "DISTINCT",
+    "ELSE",
+    "EXISTS",
+    "FOREIGN",
+    "FROM",
+    "FULL",
+    "GROUP",
+    "HAVING",
+    "IN",
+    "INDEX",
+    "INNER",
+    "IS",
+    "ISNULL",
+    "JOIN",
+    "LEFT",
+    "LIMIT",
+    "NATURAL",
+    "NOT",
+    "NOTNULL",
+    "NULL",
+    "ON",
+    "OR",
+    "ORDER",
+    "OUTER",
+    "PRIMARY",
+    "REFERENCES",
+    "RIGHT",
+    "SELECT",
+    "TABLE",
+    "THEN",
+    "UNION",
+    "UNIQUE",
+    "USING",
+    "WHEN",
+    "WHERE",
+];
+
+// keywords that start a column constraint
+const COLUMN_CONSTRAINTS: [&str; 11] = [
+    "CONSTRAINT",
+    "PRIMARY",
+    "NOT",
+    "NULL",
+    "UNIQUE",
+    "CHECK",
+    "DEFAULT",
+    "COLLATE",
+    "REFERENCES",
+    "GENERATED",
+    "AS",
+];
+
+/// Parses a single sql statement, optionally terminated by a semicolon.
+pub fn parse(sql: &str) -> Result<Statement, ParseError> {
+    let mut parser = Parser {
+        sql,
+        tokens: tokenize(sql)?,
+        pos: 0,
+    };
+
+    let statement = parser.statement()?;
+    parser.eat(&TokenKind::Semicolon);
+    match parser.peek() {
+        Some(_) => Err(parser.error()),
+        None => Ok(statement),
+    }
+}
+
+struct Parser<'a> {
+    sql: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// Returns a syntax error at the current token.
+    fn error(&self) -> ParseError {
+        match self.peek() {
+            Some(token) => ParseError::Syntax {
+                near: self.sql[token.offset..token.offset + token.len].to_string(),
+                offset: token.offset,
+            },
+            None => ParseError::Incomplete {
+                offset: self.sql.len(),
+            },
+        }
+    }
+
+    fn peek_is(&self, kind: &TokenKind) -> bool {
+        self.peek().is_some_and(|t| t.kind == *kind)
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        if self.peek_is(kind) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, kind: &TokenKind) -> Result<(), ParseError> {
+        match self.eat(kind) {
+            true => Ok(()),
+            false => Err(self.error()),
+        }
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        self.peek().is_some_and(|t| t.is_keyword(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek_keyword(keyword) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        match self.eat_keyword(keyword) {
+            true => Ok(()),
+            false => Err(self.error()),
+        }
+    }
+
+    fn peek_name(&self) -> Option<String> {
+        match &self.peek()?.kind {
+            TokenKind::Word(w) if !RESERVED.contains(&w.to_uppercase().as_str()) => {
+                Some(w.to_string())
+            }
+            TokenKind::QuotedIdent(s) | TokenKind::Str(s) => Some(s.to_string()),
+            _ => None,
+        }
+    }
+
+    fn name(&mut self) -> Result<String, ParseError> {
+        let name = self.peek_name().ok_or_else(|| self.error())?;
+        self.pos += 1;
+        Ok(name)
+    }
+
+    /// Parses `[schema.]name` and returns the name.
+    fn qualified_name(&mut self) -> Result<String, ParseError> {
+        let name = self.name()?;
+        match self.eat(&TokenKind::Dot) {
+            true => self.name(),
+            false => Ok(name),
+        }
+    }
+
+    fn if_not_exists(&mut self) -> Result<(), ParseError> {
+        if self.eat_keyword("IF") {
+            self.expect_keyword("NOT")?;
+            self.expect_keyword("EXISTS")?;
+        }
+        Ok(())
+    }
+
+    /// Skips tokens up to a comma or closing parenthesis outside of parentheses,
+    /// or up to one of `keywords`.
+    fn skip_until(&mut self, keywords: &[&str]) -> Result<(), ParseError> {
+        let mut depth = 0;
+        while let Some(token) = self.peek() {
+            match token.kind {
+                TokenKind::Comma | TokenKind::RParen if depth == 0 => return Ok(()),
+                TokenKind::LParen => depth += 1,
+                TokenKind::RParen => depth -= 1,
+                _ if depth == 0 && keywords.iter().any(|k| token.is_keyword(k)) => return Ok(()),
+                _ => {}
+            }
+            self.pos += 1;
+        }
+        Err(self.error())
+    }
+
+    fn statement(&mut self) -> Result<Statement, ParseError> {
+        if self.eat_keyword("SELECT") {
+            return self.select().map(|s| Statement::Select(Box::new(s)));
+        }
+
+        self.expect_keyword("CREATE")?;
+        if !self.eat_keyword("TEMP") {
+            self.eat_keyword("TEMPORARY");
+        }
+        if self.eat_keyword("TABLE") {
+            self.create_table().map(Statement::CreateTable)
+        } else if self.eat_keyword("UNIQUE") || self.peek_keyword("INDEX") {
+            self.expect_keyword("INDEX")?;
+            self.create_index().map(Statement::CreateIndex)
+        } else if self.eat_keyword("VIEW") {
+            self.create_view().map(Statement::CreateView)
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn select(&mut self) -> Result<Select, ParseError> {
+        let mut columns = vec![self.result_column()?];
+        while self.eat(&TokenKind::Comma) {
+            columns.push(self.result_column()?);
+        }
+
+        self.expect_keyword("FROM")?;
+        let from = self.table_ref()?;
+        let mut joins = Vec::new();
+        loop {
+            let kind = if self.eat(&TokenKind::Comma) {
+                JoinKind::Cross
+            } else if self.eat_keyword("CROSS") {
+                self.expect_keyword("JOIN")?;
+                JoinKind::Cross
+            } else if self.eat_keyword("LEFT") {
+                self.eat_keyword("OUTER");
+                self.expect_keyword("JOIN")?;
+                JoinKind::Left
+            } else if self.eat_keyword("INNER") || self.peek_keyword("JOIN") {
+                self.expect_keyword("JOIN")?;
+                JoinKind::Inner
+            } else {
+                break;
+            };
+            let table = self.table_ref()?;
+            let on = match self.eat_keyword("ON") {
+                true => Some(self.expr()?),
+                false => None,
+            };
+            joins.push(Join { kind, table, on });
+        }
+
+        let where_clause = match self.eat_keyword("WHERE") {
+            true => Some(self.expr()?),
+            false => None,
+        };
+
+        let mut group_by = Vec::new();
+        if self.eat_keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            group_by.push(self.expr()?);
+            while self.eat(&TokenKind::Comma) {
+                group_by.push(self.expr()?);
+            }
+        }
+        let having = match self.eat_keyword("HAVING") {
+            true => Some(self.expr()?),
+            false => None,
+        };
+
+        let mut order_by = Vec::new();
+        if self.eat_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            order_by.push(self.ordering_term()?);
+            while self.eat(&TokenKind::Comma) {
+                order_by.push(self.ordering_term()?);
+            }
+        }
+
+        let (mut limit, mut offset) = (None, None);
+        if self.eat_keyword("LIMIT") {
+            limit = Some(self.expr()?);
+            if self.eat_keyword("OFFSET") {
+                offset = Some(self.expr()?);
+            } else if self.eat(&TokenKind::Comma) {
+                // LIMIT <offset>, <limit>
+                offset = limit.replace(self.expr()?);
+            }
+        }
+
+        Ok(Select {
+            columns,
+            from,
+            joins,
+            where_clause,
+            group_by,
+            having,
+            order_by,
+            limit,
+            offset,
+        })
+    }
+
+    /// Parses `[schema.]name [[AS] alias]` in a FROM clause.
+    fn table_ref(&mut self) -> Result<TableRef, ParseError> {
+        let name = self.qualified_name()?;
+        let alias = match self.eat_keyword("AS") {
+            true => Some(self.name()?),
+            false => match self.peek_name() {
+                Some(alias) => {
+                    self.pos += 1;
+                    Some(alias)
+                }
+                None => None,
+            },
+        };
+
+        Ok(TableRef { name, alias })
+    }
+
+    fn ordering_term(&mut self) -> Result<OrderingTerm, ParseError> {
+        let expr = self.expr()?;
+        let descending = self.eat_keyword("DESC");
+        if !descending {
+            self.eat_keyword("ASC");
+        }
+        let nulls = match self.eat_keyword("NULLS") {
+            true if self.eat_keyword("FIRST") => Some(NullsOrder::First),
+            true => {
+                self.expect_keyword("LAST")?;
+                Some(NullsOrder::Last)
+            }
+            false => None,
+        };
+
+        Ok(OrderingTerm {
+            expr,
+            descending,
+            nulls,
+        })
+    }
+
+    fn result_column(&mut self) -> Result<ResultColumn, ParseError> {
+        if self.eat(&TokenKind::Star) {
+            return Ok(ResultColumn::All);
+        }
+
+        let expr = self.expr()?;
+        let alias = match self.eat_keyword("AS") {
+            true => Some(self.name()?),
+            false => match self.peek_name() {
+                Some(name) => {
+                    self.pos += 1;
+                    Some(name)
+                }
+                None => None,
+            },
+        };
+
+        Ok(ResultColumn::Expr { expr, alias })
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.and_expr()?;
+        while self.eat_keyword("OR") {
+            let rhs = self.and_expr()?;
+            lhs = Expr::Binary {
+                op: BinaryOp::Or,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            };
+        }
+        Ok(lhs)
+    }
+
+    fn and_expr(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.not_expr()?;
+        while self.eat_keyword("AND") {
+            let rhs = self.not_expr()?;
+            lhs = Expr::Binary {
+                op: BinaryOp::And,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            };
+        }
+        Ok(lhs)
+    }
+
+    fn not_expr(&mut self) -> Result<Expr, ParseError> {
+        if self.eat_keyword("NOT") {
+            return Ok(Expr::Not(Box::new(self.not_expr()?)));
+        }
+        self.equality()
+    }
+
+    fn equality(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.comparison()?;
+        loop {
+            let negated = self.peek_keyword("NOT")
+                && self.tokens.get(self.pos + 1).is_some_and(|t| {
+                    ["BETWEEN", "LIKE", "NULL"]
+                        .iter()
+                        .any(|keyword| t.is_keyword(keyword))
+                });
+            if negated {
+                self.pos += 1;
+            }
+            let null = || Box::new(Expr::Literal(Literal::Null));
+            let expr = if self.eat_keyword("BETWEEN") {
+                self.between(lhs)?
+            } else if self.eat_keyword("ISNULL") || (negated && self.eat_keyword("NULL")) {
+                Expr::Binary {
+                    op: BinaryOp::Is,
+                    lhs: Box::new(lhs),
+                    rhs: null(),
+                }
+            } else if self.eat_keyword("NOTNULL") {
+                Expr::Binary {
+                    op: BinaryOp::IsNot,
+                    lhs: Box::new(lhs),
+                    rhs: null(),
+                }
+            } else {
+                let op = match self.peek() {
+                    Some(t) if t.kind == TokenKind::Eq => BinaryOp::Eq,
+                    Some(t) if t.kind == TokenKind::Ne => BinaryOp::Ne,
+                    Some(t) if t.is_keyword("LIKE") => BinaryOp::Like,
+                    Some(t) if t.is_keyword("IS") => match self.tokens.get(self.pos + 1) {
+                        Some(t) if t.is_keyword("NOT") => {
+                            self.pos += 1;
+                            BinaryOp::IsNot
+                        }
+                        _ => BinaryOp::Is,
+                    },
+                    _ => return Ok(lhs),
+                };
+                self.pos += 1;
+                let rhs = self.comparison()?;
+                Expr::Binary {
+                    op,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                }
+            };
+            lhs = match negated {
+                true => Expr::Not(Box::new(expr)),
+                false => expr,
+            };
+        }
+    }
+
+    /// Parses the bounds after `expr BETWEEN`, which is `expr >= low AND expr <= high`.
+    fn between(&mut self, expr: Expr) -> Result<Expr, ParseError> {
+        let low = self.comparison()?;
+        self.expect_keyword("AND")?;
+        let high = self.comparison()?;
+        Ok(Expr::Binary {
+            op: BinaryOp::And,
+            lhs: Box::new(Expr::Binary {
+                op: BinaryOp::Ge,
+                lhs: Box::new(expr.clone()),
+                rhs: Box::new(low),
+            }),
+            rhs: Box::new(Expr::Binary {
+                op: BinaryOp::Le,
+                lhs: Box::new(expr),
+                rhs: Box::new(high),
+            }),
+        })
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.primary()?;
+        loop {
+            let op = match self.peek().map(|t| &t.kind) {
+                Some(TokenKind::Lt) => BinaryOp::Lt,
+                Some(TokenKind::Le) => BinaryOp::Le,
+                Some(TokenKind::Gt) => BinaryOp::Gt,
+                Some(TokenKind::Ge) => BinaryOp::Ge,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            let rhs = self.primary()?;
+            lhs = Expr::Binary {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            };
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let token = self.peek().ok_or_else(|| self.error())?;
+        let expr = match &token.kind {
+            TokenKind::Word(_) if token.is_keyword("NULL") => Expr::Literal(Literal::Null),
+            TokenKind::Word(name)
+                if self
+                    .tokens
+                    .get(self.pos + 1)
+                    .is_some_and(|t| t.kind == TokenKind::LParen) =>
+            {
+                let name = name.to_lowercase();
+                self.pos += 2;
+                return self.function_call(name);
+            }
+            TokenKind::Word(_) | TokenKind::QuotedIdent(_) => {
+                let name = self.name()?;
+                return Ok(match self.eat(&TokenKind::Dot) {
+                    true => Expr::Column {
+                        table: Some(name),
+                        name: self.name()?,
+                    },
+                    false => Expr::Column { table: None, name },
+                });
+            }
+            TokenKind::Str(s) => Expr::Literal(Literal::Text(s.to_string())),
+            TokenKind::Integer(n) => Expr::Literal(Literal::Integer(*n)),
+            TokenKind::Real(r) => Expr::Literal(Literal::Real(*r)),
+            TokenKind::Blob(b) => Expr::Literal(Literal::Blob(b.clone())),
+            TokenKind::Minus => {
+                self.pos += 1;
+                return match self.advance().map(|t| t.kind) {
+                    Some(TokenKind::Integer(n)) => Ok(Expr::Literal(Literal::Integer(-n))),
+                    Some(TokenKind::Real(r)) => Ok(Expr::Literal(Literal::Real(-r))),
+                    _ => {
+                        self.pos -= 1;
+                        Err(self.error())
+                    }
+                };
+            }
+            TokenKind::LParen => {
+                self.pos += 1;
+                let expr = self.expr()?;
+                self.expect(&TokenKind::RParen)?;
+                return Ok(expr);
+            }
+            _ => return Err(self.error()),
+        };
+        self.pos += 1;
+        Ok(expr)
+    }
+
+    /// Parses the arguments of a call to `name` after the opening parenthesis.
+    fn function_call(&mut self, name: String) -> Result<Expr, ParseError> {
+        let distinct = self.eat_keyword("DISTINCT");
+        let mut args = Vec::new();
+        if distinct || !(self.eat(&TokenKind::Star) || self.peek_is(&TokenKind::RParen)) {
+            args.push(self.expr()?);
+            while self.eat(&TokenKind::Comma) {
+                args.push(self.expr()?);
+            }
+        }
+        self.expect(&TokenKind::RParen)?;
+
+        Ok(Expr::Function {
+            name,
+            distinct,
+            args,
+        })
+    }
+
+    fn create_table(&mut self) -> Result<CreateTable, ParseError> {
+        self.if_not_exists()?;
+        let name = self.qualified_name()?;
+        self.expect(&TokenKind::LParen)?;
+
+        let mut columns = Vec::new();
+        let mut constraints = Vec::new();
+        loop {
+            if ["CONSTRAINT", "PRIMARY", "UNIQUE", "CHECK", "FOREIGN"]
+                .iter()
+                .any(|k| self.peek_keyword(k))
+            {
+                constraints.push(self.table_constraint()?);
+            } else {
+                columns.push(self.column_def()?);
+            }
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect(&TokenKind::RParen)?;
+
+        let mut without_rowid = false;
+        // comma separated table options
+        while self.peek().is_some_and(|t| t.kind != TokenKind::Semicolon) {
+            if self.eat_keyword("WITHOUT") {
+                self.expect_keyword("ROWID")?;
+                without_rowid = true;
+            } else if !self.eat_keyword("STRICT") {
+                return Err(self.error());
+            }
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+
+        Ok(CreateTable {
+            name,
+            columns,
+            constraints,
+            without_rowid,
+        })
+    }
+
+    fn column_def(&mut self) -> Result<ColumnDef, ParseError> {
+        let name = self.name()?;
+
+        // the type is a run of words, optionally followed by a size like (10, 2)
+        let type_start = self.peek().map(|t| t.offset);
+        let mut type_end = None;
+        while let Some(token) = self.peek() {
+            if !matches!(token.kind, TokenKind::Word(_) | TokenKind::QuotedIdent(_))
+                || COLUMN_CONSTRAINTS.iter().any(|k| token.is_keyword(k))
+            {
+                break;
+            }
+            type_end = Some(token.offset + token.len);
+            self.pos += 1;
+        }
+        if type_end.is_some() && self.eat(&TokenKind::LParen) {
+            self.skip_until(&[])?;
+            while self.eat(&TokenKind::Comma) {
+                self.skip_until(&[])?;
+            }
+            type_end = self.peek().map(|t| t.offset + t.len);
+            self.expect(&TokenKind::RParen)?;
+        }
+        let type_name = match (type_start, type_end) {
+            (Some(start), Some(end)) => self.sql[start..end].to_string(),
+            _ => String::new(),
+        };
+
+        let mut constraints = Vec::new();
+        while !matches!(
+            self.peek().map(|t| &t.kind),
+            None | Some(TokenKind::Comma | TokenKind::RParen)
+        ) {
+            if self.eat_keyword("CONSTRAINT") {
+                self.name()?;
+            }
+            let constraint = if self.eat_keyword("PRIMARY") {
+                self.expect_keyword("KEY")?;
+                let descending = self.eat_keyword("DESC");
+                ColumnConstraint::PrimaryKey { descending }
+            } else if self.eat_keyword("UNIQUE") {
+                ColumnConstraint::Unique
+            } else if self.eat_keyword("COLLATE") {
+                ColumnConstraint::Collate(self.name()?)
+            } else if COLUMN_CONSTRAINTS.iter().any(|k| self.peek_keyword(k)) {
+                self.pos += 1;
+                ColumnConstraint::Other
+            } else {
+                return Err(self.error());
+            };
+            // sort order, conflict clauses, constraint arguments
+            self.skip_until(&COLUMN_CONSTRAINTS)?;
+            constraints.push(constraint);
+        }
+
+        Ok(ColumnDef {
+            name,
+            type_name,
+            constraints,
+        })
+    }
+
+    fn table_constraint(&mut self) -> Result<TableConstraint, ParseError> {
+        if self.eat_keyword("CONSTRAINT") {
+            self.name()?;
+        }
+
+        let constraint = if self.eat_keyword("PRIMARY") {
+            self.expect_keyword("KEY")?;
+            TableConstraint::PrimaryKey(self.indexed_columns()?)
+        } else if self.eat_keyword("UNIQUE") {
+            TableConstraint::Unique(self.indexed_columns()?)
+        } else if self.eat_keyword("CHECK") || self.eat_keyword("FOREIGN") {
+            TableConstraint::Other
+        } else {
+            return Err(self.error());
+        };
+        self.skip_until(&[])?;
+
+        Ok(constraint)
+    }
+
+    /// Parses a parenthesized list of indexed columns.
+    fn indexed_columns(&mut self) -> Result<Vec<IndexedColumn>, ParseError> {
+        self.expect(&TokenKind::LParen)?;
+        let mut columns = Vec::new();
+        loop {
+            let first = self.pos;
+            let name = self.peek_name();
+            self.skip_until(&["COLLATE", "ASC", "DESC"])?;
+            let name = match (name, self.pos - first) {
+                (_, 0) => return Err(self.error()),
+                (Some(name), 1) => name,
+                // expressions are kept as written, they never match a column
+                _ => {
+                    let last = &self.tokens[self.pos - 1];
+                    self.sql[self.tokens[first].offset..last.offset + last.len].to_string()
+                }
+            };
+            let collation = match self.eat_keyword("COLLATE") {
+                true => Some(self.name()?),
+                false => None,
+            };
+            let descending = self.eat_keyword("DESC");
+            if !descending {
+                self.eat_keyword("ASC");
+            }
+            columns.push(IndexedColumn {
+                name,
+                descending,
+                collation,
+            });
+
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect(&TokenKind::RParen)?;
+        Ok(columns)
+    }
+
+    fn create_index(&mut self) -> Result<CreateIndex, ParseError> {
+        self.if_not_exists()?;
+        let name = self.qualified_name()?;
+        self.expect_keyword("ON")?;
+        let table_name = self.name()?;
+        let columns = self.indexed_columns()?;
+        let where_clause = match self.eat_keyword("WHERE") {
+            true => Some(self.expr()?),
+            false => None,
+        };
+
+        Ok(CreateIndex {
+            name,
+            table_name,
+            columns,
+            where_clause,
+        })
+    }
+
+    fn create_view(&mut self) -> Result<CreateView, ParseError> {
+        self.if_not_exists()?;
+        let name = self.qualified_name()?;
+
+        let columns = match self.eat(&TokenKind::LParen) {
+            true => {
+                let mut columns = vec![self.name()?];
+                while self.eat(&TokenKind::Comma) {
+                    columns.push(self.name()?);
+                }
+                self.expect(&TokenKind::RParen)?;
+                Some(columns)
+            }
+            false => None,
+        };
+
+        self.expect_keyword("AS")?;
+        // the SELECT is parsed when the view is queried
+        let start = self.peek().ok_or_else(|| self.error())?.offset;
+        let select_sql = self.sql[start..]
+            .trim_end()
+            .trim_end_matches(';')
+            .to_string();
+        self.pos = self.tokens.len();
+
+        Ok(CreateView {
+            name,
+            columns,
+            select_sql,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let statement = parse(
+            "SELECT name, \"size range\" FROM main.companies WHERE country = 'it''s' AND id = 5;",
+        )
+        .unwrap();
+        assert_eq!(
+            statement,
+            Statement::Select(Box::new(Select {
+                columns: vec![
+                    ResultColumn::Expr {
+                        expr: Expr::column("name"),
+                        alias: None
+                    },
+                    ResultColumn::Expr {
+                        expr: Expr::column("size range"),
+                        alias: None
+                    },
+                ],
+                from: TableRef {
+                    name: "companies".to_string(),
+                    alias: None
+                },
+                joins: vec![],
+                where_clause: Some(Expr::Binary {
+                    op: BinaryOp::And,
+                    lhs: Box::new(Expr::Binary {
+                        op: BinaryOp::Eq,
+                        lhs: Box::new(Expr::column("country")),
+                        rhs: Box::new(Expr::Literal(Literal::Text("it's".to_string()))),
+                    }),
+                    rhs: Box::new(Expr::Binary {
+                        op: BinaryOp::Eq,
+                        lhs: Box::new(Expr::column("id")),
+                        rhs: Box::new(Expr::Literal(Literal::Integer(5))),
+                    }),
+                }),
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: None,
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_create_table() {
+        let sql = "CREATE TABLE IF NOT EXISTS \"t\" (\n  id integer primary key autoincrement,\n  -- a comment\n  name varchar(10, 2) NOT NULL DEFAULT 'a,b',\n  c text unique collate rtrim,\n  CONSTRAINT pk UNIQUE (name COLLATE nocase DESC, c),\n  CHECK (c != 'x')\n) WITHOUT ROWID";
+        let Statement::CreateTable(table) = parse(sql).unwrap() else {
+            panic!("not a create table");
+        };
+        assert_eq!(table.name, "t");
+        assert_eq!(
+            table
+                .columns
+                .iter()
+                .map(|c| (c.name.as_str(), c.type_name.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("id", "integer"), ("name", "varchar(10, 2)"), ("c", "text")]
+        );
+        assert_eq!(
+            table.columns[0].constraints,
+            vec![ColumnConstraint::PrimaryKey { descending: false }]
+        );
+        assert_eq!(
+            table.columns[2].constraints,
+            vec![
+                ColumnConstraint::Unique,
+                ColumnConstraint::Collate("rtrim".to_string())
+            ]
+        );
+        assert_eq!(
+            table.constraints,
+            vec![
+                TableConstraint::Unique(vec![
+                    IndexedColumn {
+                        name: "name".to_string(),
+                        descending: true,
+                        collation: Some("nocase".to_string())
+                    },
+                    IndexedColumn {
+                        name: "c".to_string(),
+                        descending: false,
+                        collation: None
+                    }
+                ]),
+                TableConstraint::Other
+            ]
+        );
+        assert!(table.without_rowid);
+    }
+
+    #[test]
+    fn parses_create_index() {
+        let sql = "CREATE UNIQUE INDEX i ON t (a COLLATE NoCase, b DESC) WHERE a IS NOT NULL";
+        let Statement::CreateIndex(idx) = parse(sql).unwrap() else {
+            panic!("not a create index");
+        };
+        assert_eq!(idx.table_name, "t");
+        assert_eq!(
+            idx.columns,
+            vec![
+                IndexedColumn {
+                    name: "a".to_string(),
+                    descending: false,
+                    collation: Some("NoCase".to_string())
+                },
+                IndexedColumn {
+                    name: "b".to_string(),
+                    descending: true,
+                    collation: None
+                }
+            ]
+        );
+        assert_eq!(
+            idx.where_clause,
+            Some(Expr::Binary {
+                op: BinaryOp::IsNot,
+                lhs: Box::new(Expr::column("a")),
+                rhs: Box::new(Expr::Literal(Literal::Null)),
+            })
+        );
+    }
+
+    #[test]
+    fn parses_order_by() {
+        let Statement::Select(select) =
+            parse("select a, b from t order by b desc nulls first, 1").unwrap()
+        else {
+            panic!("not a select");
+        };
+        assert_eq!(
+            select.order_by,
+            vec![
+                OrderingTerm {
+                    expr: Expr::column("b"),
+                    descending: true,
+                    nulls: Some(NullsOrder::First)
+                },
+                OrderingTerm {
+                    expr: Expr::Literal(Literal::Integer(1)),
+                    descending: false,
+                    nulls: None
+                }
+            ]
+        );
+
+        let Statement::Select(select) = parse("select a from t limit 5, 10").unwrap() else {
+            panic!("not a select");
+        };
+        assert_eq!(select.limit, Some(Expr::Literal(Literal::Integer(10))));
+        assert_eq!(select.offset, Some(Expr::Literal(Literal::Integer(5))));
+    }
+
+    #[test]
+    fn parses_joins() {
+        let Statement::Select(select) =
+            parse("select e.name from emp as e left join dept d on d.id = e.dept_id, proj")
+                .unwrap()
+        else {
+            panic!("not a select");
+        };
+        assert_eq!(
+            select.columns,
+            vec![ResultColumn::Expr {
+                expr: Expr::Column {
+                    table: Some("e".to_string()),
+                    name: "name".to_string()
+                },
+                alias: None
+            }]
+        );
+        assert_eq!(select.from.qualifier(), "e");
+        assert_eq!(select.joins.len(), 2);
+        assert_eq!(select.joins[0].kind, JoinKind::Left);
+        assert_eq!(select.joins[0].table.qualifier(), "d");
+        assert!(select.joins[0].on.is_some());
+        assert_eq!(
+            select.joins[1],
+            Join {
+                kind: JoinKind::Cross,
+                table: TableRef {
+                    name: "proj".to_string(),
+                    alias: None
+                },
+                on: None
+            }
+        );
+    }
+
+    #[test]
+    fn reports_error_position() {
+        let sql = "SELECT FROM t";
+        assert_eq!(
+            parse(sql).unwrap_err(),
+            ParseError::Syntax {
+                near: "FROM".to_string(),
+                offset: 7
+            }
+        );
+        assert_eq!(
+            parse("select a from t where").unwrap_err().to_string(),
+            "incomplete input"
+        );
+    }
+}
@@ -1,108 +1,611 @@
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::{cmp::Ordering, collections::BTreeMap};
 
 use anyhow::{anyhow, Result};
-use regex::Regex;
 
-#[derive(Debug)]
+use crate::{
+    ast::{
+        BinaryOp, ColumnConstraint, Expr, IndexedColumn, Join, Literal, OrderingTerm, ResultColumn,
+        Select, Statement, TableConstraint, TableRef,
+    },
+    eval,
+    page::Column,
+    parser, util,
+    value::Value,
+};
+
+#[derive(Debug, Clone)]
 pub struct SelectQuery {
     pub table_name: String,
-    pub columns: HashMap<String, usize>,
-    pub where_column: Option<String>,
-    pub where_value: Option<String>,
+    pub alias: Option<String>,
+    // the other tables in the FROM clause, in order
+    pub joins: Vec<Join>,
+    // the expressions of the result columns
+    pub columns: Vec<Expr>,
+    // the alias or the column name of each result column, which become the column
+    // names of a view
+    pub column_names: Vec<String>,
+    // the alias of each result column, if it has one
+    pub aliases: Vec<Option<String>>,
+    pub where_clause: Option<Expr>,
+    pub group_by: Vec<Expr>,
+    pub having: Option<Expr>,
+    // terms referring to result columns by number or alias are replaced by the column
+    pub order_by: Vec<OrderingTerm>,
+    // None if the number of rows is not limited
+    pub limit: Option<usize>,
+    pub offset: usize,
 }
 
 impl SelectQuery {
     pub fn from_query_string(query_string: &str) -> Result<SelectQuery> {
-        let re = Regex::new(r"(?i)SELECT (?P<columns>[,|\s|\w]+) FROM (?P<table>\w+)(?: WHERE(?P<condition>[\s|\w]+=['\s|\w]+))?").unwrap();
-        let caps = re.captures(query_string).unwrap();
-
-        let table_name = caps
-            .name("table")
-            .ok_or(anyhow!("can't get table name from query string"))?
-            .as_str()
-            .to_string();
-
-        let column_caps = caps
-            .name("columns")
-            .ok_or(anyhow!("can't get table name from query string"))?
-            .as_str()
-            .to_string();
-
-        let mut columns: HashMap<String, usize> = Default::default();
-        for (i, c) in column_caps
-            .split(",")
-            .map(|c| c.trim().to_string())
-            .enumerate()
-        {
-            columns.insert(c, i);
-        }
-
-        let condition = caps.name("condition");
-
-        let mut where_column = None;
-        let mut where_value = None;
-        if condition.is_some() {
-            let mut parts = condition.unwrap().as_str().split('=');
-            where_column = Some(
-                parts
-                    .next()
-                    .ok_or(anyhow!("can't parse where_column from condition"))?
-                    .trim()
-                    .trim_matches('\'')
-                    .to_string(),
-            );
-            where_value = Some(
-                parts
-                    .next()
-                    .ok_or(anyhow!("can't parse where_value from condition"))?
-                    .trim()
-                    .trim_matches('\'')
-                    .to_string(),
-            );
+        let select = match parser::parse(query_string)? {
+            Statement::Select(select) => fold_select(*select)?,
+            _ => return Err(anyhow!("not a select query: {query_string}")),
         };
 
-        Ok(Self {
-            table_name,
+        let mut columns = Vec::new();
+        let mut column_names = Vec::new();
+        // aliases of the result columns
+        let mut aliases = Vec::new();
+        for (i, c) in select.columns.iter().enumerate() {
+            match c {
+                ResultColumn::Expr { expr, alias } => {
+                    columns.push(expr.clone());
+                    column_names.push(match (alias, expr) {
+                        (Some(alias), _) => alias.to_string(),
+                        (None, Expr::Column { name, .. }) => name.to_string(),
+                        (None, _) => format!("column{}", i + 1),
+                    });
+                    aliases.push(alias.clone());
+                }
+                ResultColumn::All => {
+                    return Err(anyhow!("can't select *, only expressions are supported"))
+                }
+            }
+        }
+
+        let mut order_by = Vec::new();
+        for (i, term) in select.order_by.iter().enumerate() {
+            let expr = match &term.expr {
+                Expr::Literal(Literal::Integer(k)) => usize::try_from(*k)
+                    .ok()
+                    .and_then(|k| columns.get(k.checked_sub(1)?))
+                    .ok_or(anyhow!(
+                        "{} ORDER BY term out of range - should be between 1 and {}",
+                        ordinal(i + 1),
+                        columns.len()
+                    ))?
+                    .clone(),
+                Expr::Column { table: None, name } => {
+                    match aliases.iter().position(|a| a.as_ref() == Some(name)) {
+                        Some(k) => columns[k].clone(),
+                        None => term.expr.clone(),
+                    }
+                }
+                expr => expr.clone(),
+            };
+            order_by.push(OrderingTerm {
+                expr,
+                ..term.clone()
+            });
+        }
+
+        // GROUP BY terms can refer to result columns by number too
+        let mut group_by = Vec::new();
+        for (i, expr) in select.group_by.into_iter().enumerate() {
+            group_by.push(match expr {
+                Expr::Literal(Literal::Integer(k)) => usize::try_from(k)
+                    .ok()
+                    .and_then(|k| columns.get(k.checked_sub(1)?))
+                    .ok_or(anyhow!(
+                        "{} GROUP BY term out of range - should be between 1 and {}",
+                        ordinal(i + 1),
+                        columns.len()
+                    ))?
+                    .clone(),
+                expr => expr,
+            });
+        }
+
+        let mut query = Self {
+            table_name: select.from.name.to_string(),
+            alias: select.from.alias.clone(),
+            joins: select.joins,
             columns,
-            where_column,
-            where_value,
-        })
+            column_names,
+            aliases,
+            where_clause: select.where_clause,
+            group_by,
+            having: select.having,
+            order_by,
+            // a negative limit means no limit and a negative offset none at all
+            limit: match &select.limit {
+                Some(expr) => usize::try_from(limit_value(expr)?).ok(),
+                None => None,
+            },
+            offset: match &select.offset {
+                Some(expr) => usize::try_from(limit_value(expr)?).unwrap_or(0),
+                None => 0,
+            },
+        };
+
+        // the columns of a single table don't need their qualifier
+        if query.joins.is_empty() {
+            let qualifier = select.from.qualifier();
+            let unqualify = |table: Option<&str>, name: &str| match table {
+                Some(table) if table != qualifier => Err(anyhow!("no such column: {table}.{name}")),
+                _ => Ok(Expr::column(name)),
+            };
+            let unqualify = |expr: &mut Expr| -> Result<()> {
+                *expr = expr.map_columns(&unqualify)?;
+                Ok(())
+            };
+            query.columns.iter_mut().try_for_each(unqualify)?;
+            query.where_clause.iter_mut().try_for_each(unqualify)?;
+            query.group_by.iter_mut().try_for_each(unqualify)?;
+            query.having.iter_mut().try_for_each(unqualify)?;
+            query
+                .order_by
+                .iter_mut()
+                .try_for_each(|term| unqualify(&mut term.expr))?;
+        }
+
+        Ok(query)
+    }
+
+    /// Replaces the columns of GROUP BY and HAVING that name no column of the FROM
+    /// clause but a result column alias by the expression of that result column.
+    pub fn resolve_aliases<F>(&mut self, is_column: F) -> Result<()>
+    where
+        F: Fn(Option<&str>, &str) -> bool,
+    {
+        let (columns, aliases) = (&self.columns, &self.aliases);
+        let resolve = |table: Option<&str>, name: &str| {
+            let alias = match table {
+                Some(_) => None,
+                None if is_column(None, name) => None,
+                None => aliases.iter().position(|a| a.as_deref() == Some(name)),
+            };
+            Ok(match alias {
+                Some(i) => columns[i].clone(),
+                None => Expr::Column {
+                    table: table.map(str::to_string),
+                    name: name.to_string(),
+                },
+            })
+        };
+        for expr in self.group_by.iter_mut().chain(&mut self.having) {
+            *expr = expr.map_columns(&resolve)?;
+        }
+
+        for expr in &self.group_by {
+            let mut aggregate = false;
+            expr.visit(&mut |e| match e {
+                Expr::Function { name, .. } if eval::AGGREGATES.contains(&name.as_str()) => {
+                    aggregate = true;
+                    false
+                }
+                _ => true,
+            });
+            if aggregate {
+                return Err(anyhow!(
+                    "aggregate functions are not allowed in the GROUP BY clause"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the expressions that are evaluated once per result row: the result
+    /// columns, HAVING and the ORDER BY terms.
+    fn result_exprs(&self) -> impl Iterator<Item = &Expr> {
+        self.columns
+            .iter()
+            .chain(&self.having)
+            .chain(self.order_by.iter().map(|term| &term.expr))
+    }
+
+    /// Returns the distinct aggregate function calls of the result expressions.
+    pub fn aggregates(&self) -> Vec<&Expr> {
+        let mut aggregates = Vec::new();
+        for expr in self.result_exprs() {
+            expr.visit(&mut |e| match e {
+                Expr::Function { name, .. } if eval::AGGREGATES.contains(&name.as_str()) => {
+                    if !aggregates.contains(&e) {
+                        aggregates.push(e);
+                    }
+                    false
+                }
+                _ => true,
+            });
+        }
+        aggregates
+    }
+
+    /// Returns the columns the result expressions use outside of aggregate functions.
+    pub fn bare_columns(&self) -> Vec<&Expr> {
+        let mut columns = Vec::new();
+        for expr in self.result_exprs() {
+            expr.visit(&mut |e| match e {
+                Expr::Column { .. } => {
+                    if !columns.contains(&e) {
+                        columns.push(e);
+                    }
+                    true
+                }
+                Expr::Function { name, .. } => !eval::AGGREGATES.contains(&name.as_str()),
+                _ => true,
+            });
+        }
+        columns
+    }
+
+    /// Returns true if the rows are grouped, which aggregate functions do even
+    /// without GROUP BY.
+    pub fn is_aggregate(&self) -> bool {
+        !self.group_by.is_empty() || self.having.is_some() || !self.aggregates().is_empty()
+    }
+
+    /// Returns the literal the column must be equal to for the WHERE clause to hold,
+    /// if there is such a condition. Only IS makes a column equal to NULL.
+    pub fn where_value(&self, column_name: &str) -> Option<&Literal> {
+        self.where_clause
+            .iter()
+            .flat_map(Expr::conjuncts)
+            .find_map(|expr| match expr {
+                Expr::Binary {
+                    op: op @ (BinaryOp::Eq | BinaryOp::Is),
+                    lhs,
+                    rhs,
+                } => match (lhs.as_ref(), rhs.as_ref()) {
+                    (Expr::Column { table: None, name }, Expr::Literal(literal))
+                    | (Expr::Literal(literal), Expr::Column { table: None, name })
+                        if name == column_name
+                            && (*literal != Literal::Null || *op == BinaryOp::Is) =>
+                    {
+                        Some(literal)
+                    }
+                    _ => None,
+                },
+                _ => None,
+            })
+    }
+}
+
+/// Folds the table, alias and column names of a SELECT to ASCII lowercase, since
+/// SQLite compares names without regard to case, quoted or not.
+fn fold_select(mut select: Select) -> Result<Select> {
+    let fold_table = |table: &mut TableRef| {
+        table.name.make_ascii_lowercase();
+        if let Some(alias) = &mut table.alias {
+            alias.make_ascii_lowercase();
+        }
+    };
+
+    for column in &mut select.columns {
+        if let ResultColumn::Expr { expr, alias } = column {
+            fold_expr(expr)?;
+            if let Some(alias) = alias {
+                alias.make_ascii_lowercase();
+            }
+        }
+    }
+    fold_table(&mut select.from);
+    for join in &mut select.joins {
+        fold_table(&mut join.table);
+        join.on.iter_mut().try_for_each(fold_expr)?;
     }
+    select.where_clause.iter_mut().try_for_each(fold_expr)?;
+    select.group_by.iter_mut().try_for_each(fold_expr)?;
+    select.having.iter_mut().try_for_each(fold_expr)?;
+    select
+        .order_by
+        .iter_mut()
+        .try_for_each(|term| fold_expr(&mut term.expr))?;
+
+    Ok(select)
+}
+
+/// Folds the table and column names of an expression to ASCII lowercase.
+fn fold_expr(expr: &mut Expr) -> Result<()> {
+    *expr = expr.map_columns(&|table, name| {
+        Ok(Expr::Column {
+            table: table.map(str::to_ascii_lowercase),
+            name: name.to_ascii_lowercase(),
+        })
+    })?;
+    Ok(())
+}
+
+/// Folds the names and collating sequences of indexed columns to ASCII lowercase.
+fn fold_columns(columns: Vec<IndexedColumn>) -> Vec<IndexedColumn> {
+    columns
+        .into_iter()
+        .map(|c| IndexedColumn {
+            name: c.name.to_ascii_lowercase(),
+            descending: c.descending,
+            collation: c.collation.map(|name| name.to_ascii_lowercase()),
+        })
+        .collect()
+}
+
+/// Evaluates the expression of a LIMIT or OFFSET, which must be an integer.
+fn limit_value(expr: &Expr) -> Result<i64> {
+    let value = Affinity::Integer.apply(eval::eval(expr, &|_: &str| None)?);
+    value.as_i64().ok_or(anyhow!("datatype mismatch"))
+}
+
+/// Returns "1st", "2nd", "3rd", "4th" and so on.
+fn ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
 }
 
 #[derive(Debug)]
 pub struct CreateTableQuery {
     pub column_orders: BTreeMap<String, usize>,
+    pub column_affinities: BTreeMap<String, Affinity>,
+    // column_name -> collating sequence, for the columns declared with COLLATE
+    pub column_collations: BTreeMap<String, String>,
+    // columns of the automatic indexes that back UNIQUE and PRIMARY KEY constraints,
+    // the n-th entry belongs to sqlite_autoindex_<table>_<n + 1>, except that the
+    // primary key of a WITHOUT ROWID table is the table itself
+    pub autoindex_columns: Vec<Vec<IndexedColumn>>,
+    pub without_rowid: bool,
+    // primary key columns in key order, with their collating sequence
+    pub primary_key: Vec<IndexedColumn>,
+    // the INTEGER PRIMARY KEY column that holds the rowid
+    pub rowid_alias: Option<String>,
 }
 
 impl CreateTableQuery {
     pub fn from_sql(sql: &str) -> anyhow::Result<CreateTableQuery> {
-        let re =
-            Regex::new(r#"CREATE TABLE \"?\w+\"?\n?\s?\(\n?(?P<columns>(?:\n|.)+)\)"#).unwrap();
-        let caps = re
-            .captures(sql)
-            .ok_or(anyhow!("can't parse columns from {}", sql))?;
-        let columns = &caps["columns"];
+        let table = match parser::parse(sql)? {
+            Statement::CreateTable(table) => table,
+            _ => return Err(anyhow!("can't parse columns from {}", sql)),
+        };
+
         let mut column_orders = BTreeMap::new();
-        for (i, mut c) in columns.split(",").enumerate() {
-            c = c.trim();
-            if c.starts_with('"') {
-                c = c
-                    .split('"')
-                    .nth(1)
-                    .ok_or(anyhow!("bad format of the column {c}"))?;
-                column_orders.insert(c.to_string(), i);
-                continue;
+        let mut column_affinities = BTreeMap::new();
+        let mut column_collations = BTreeMap::new();
+        let mut declared_types = BTreeMap::new();
+        let mut autoindex_columns: Vec<Vec<IndexedColumn>> = Vec::new();
+        let mut primary_key = None;
+        // INTEGER PRIMARY KEY DESC on a column is not a rowid alias
+        let mut descending_column_key = false;
+        // column names are kept in lowercase, they are compared without regard to case
+        for column in &table.columns {
+            let name = &column.name.to_ascii_lowercase();
+            let collation = column.constraints.iter().find_map(|c| match c {
+                ColumnConstraint::Collate(collation) => Some(collation.to_ascii_lowercase()),
+                _ => None,
+            });
+            let indexed = |descending| {
+                vec![IndexedColumn {
+                    name: name.to_string(),
+                    descending,
+                    collation: collation.clone(),
+                }]
+            };
+            for constraint in &column.constraints {
+                match constraint {
+                    ColumnConstraint::PrimaryKey { descending } => {
+                        primary_key = Some(autoindex_columns.len());
+                        autoindex_columns.push(indexed(*descending));
+                        descending_column_key = *descending;
+                    }
+                    ColumnConstraint::Unique => autoindex_columns.push(indexed(false)),
+                    ColumnConstraint::Collate(_) | ColumnConstraint::Other => {}
+                }
+            }
+
+            column_orders.insert(name.to_string(), column_orders.len());
+            column_affinities.insert(
+                name.to_string(),
+                Affinity::from_declared_type(&column.type_name),
+            );
+            if let Some(collation) = collation {
+                column_collations.insert(name.to_string(), collation);
+            }
+            declared_types.insert(name.to_string(), column.type_name.to_uppercase());
+        }
+        // columns of a constraint without COLLATE use the collating sequence of the column
+        let with_collations = |columns: Vec<IndexedColumn>| {
+            fold_columns(columns)
+                .into_iter()
+                .map(|c| IndexedColumn {
+                    collation: c
+                        .collation
+                        .or_else(|| column_collations.get(&c.name).cloned()),
+                    ..c
+                })
+                .collect()
+        };
+        for constraint in table.constraints {
+            match constraint {
+                TableConstraint::PrimaryKey(columns) => {
+                    primary_key = Some(autoindex_columns.len());
+                    autoindex_columns.push(with_collations(columns));
+                    descending_column_key = false;
+                }
+                TableConstraint::Unique(columns) => {
+                    autoindex_columns.push(with_collations(columns))
+                }
+                TableConstraint::Other => {}
+            }
+        }
+
+        let without_rowid = table.without_rowid;
+
+        let primary_key_columns = primary_key
+            .map(|i| autoindex_columns[i].clone())
+            .unwrap_or_default();
+        let mut rowid_alias = None;
+        // the primary key index of a WITHOUT ROWID table keeps its number, though it
+        // is the table itself and has no row in sqlite_schema
+        if let Some(i) = primary_key.filter(|_| !without_rowid) {
+            if let [column] = primary_key_columns.as_slice() {
+                // INTEGER PRIMARY KEY is an alias for the rowid and doesn't get an index
+                if declared_types.get(&column.name).map(String::as_str) == Some("INTEGER")
+                    && !descending_column_key
+                {
+                    autoindex_columns.remove(i);
+                    rowid_alias = Some(column.name.to_string());
+                }
+            }
+        }
+
+        if without_rowid {
+            // records hold the primary key columns first, then the rest in table order
+            let mut table_order = column_orders.iter().collect::<Vec<_>>();
+            table_order.sort_by_key(|(_, order)| **order);
+            let record_order = primary_key_columns.iter().map(|c| &c.name).chain(
+                table_order
+                    .into_iter()
+                    .map(|(name, _)| name)
+                    .filter(|name| primary_key_columns.iter().all(|c| c.name != **name)),
+            );
+            column_orders = record_order
+                .enumerate()
+                .map(|(i, name)| (name.to_string(), i))
+                .collect();
+        }
+        // constraints on the same columns with the same collating sequences share one
+        // index, whatever their sort order, and don't use up a number
+        let key = |columns: &[IndexedColumn]| {
+            columns
+                .iter()
+                .map(|c| {
+                    (
+                        c.name.to_string(),
+                        c.collation.as_deref().unwrap_or("binary").to_string(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut seen = Vec::new();
+        autoindex_columns.retain(|columns| {
+            let key = key(columns);
+            let is_new = !seen.contains(&key);
+            seen.push(key);
+            is_new
+        });
+
+        Ok(CreateTableQuery {
+            column_orders,
+            column_affinities,
+            column_collations,
+            autoindex_columns,
+            without_rowid,
+            primary_key: primary_key_columns,
+            rowid_alias,
+        })
+    }
+}
+
+/// Column type affinity as determined from the declared type of a column.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Affinity {
+    Text,
+    Numeric,
+    Integer,
+    Real,
+    Blob,
+}
+
+impl Affinity {
+    /// Determines the affinity from the declared type of a column.
+    pub fn from_declared_type(declared_type: &str) -> Affinity {
+        let declared_type = declared_type.to_uppercase();
+
+        if declared_type.contains("INT") {
+            Affinity::Integer
+        } else if ["CHAR", "CLOB", "TEXT"]
+            .iter()
+            .any(|t| declared_type.contains(t))
+        {
+            Affinity::Text
+        } else if declared_type.is_empty() || declared_type.contains("BLOB") {
+            Affinity::Blob
+        } else if ["REAL", "FLOA", "DOUB"]
+            .iter()
+            .any(|t| declared_type.contains(t))
+        {
+            Affinity::Real
+        } else {
+            Affinity::Numeric
+        }
+    }
+
+    /// Converts a value the way storing it in a column of this affinity would.
+    pub fn apply(&self, value: Column) -> Column {
+        match (self, value) {
+            // numbers are stored as the text they print as
+            (Affinity::Text, c) if c.as_i64().is_some() || matches!(c, Column::F64(_)) => {
+                Column::Str(Value::from(c).to_string())
             }
-            c = c
-                .trim()
-                .split(" ")
-                .next()
-                .ok_or(anyhow!("bad format of the column {c}"))?;
+            (Affinity::Integer | Affinity::Numeric, Column::Str(s)) => {
+                match util::parse_numeric(&s) {
+                    // reals without a fractional part are stored as integers
+                    Some(Column::F64(r))
+                        if r.fract() == 0.0 && (i64::MIN as f64..i64::MAX as f64).contains(&r) =>
+                    {
+                        Column::I64(r as i64)
+                    }
+                    Some(number) => number,
+                    None => Column::Str(s),
+                }
+            }
+            (Affinity::Real, Column::Str(s)) => match util::parse_numeric(&s) {
+                Some(number) => Column::F64(number.as_f64().unwrap()),
+                None => Column::Str(s),
+            },
+            (Affinity::Real, c) if c.as_i64().is_some() => Column::F64(c.as_f64().unwrap()),
+            (_, c) => c,
+        }
+    }
+}
 
-            column_orders.insert(c.to_string(), i);
+/// A built-in collating sequence, which decides how text values compare.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Collation {
+    Binary,
+    // ASCII letters compare without regard to case
+    NoCase,
+    // trailing spaces are ignored
+    RTrim,
+}
+
+impl Collation {
+    /// Returns the collating sequence called `name`, BINARY if there is no name and
+    /// None if it isn't a built-in one.
+    pub fn from_name(name: Option<&str>) -> Option<Collation> {
+        match name.map(str::to_ascii_lowercase).as_deref() {
+            None | Some("binary") => Some(Collation::Binary),
+            Some("nocase") => Some(Collation::NoCase),
+            Some("rtrim") => Some(Collation::RTrim),
+            Some(_) => None,
+        }
+    }
+
+    /// Compares two values like `Column::compare`, but text by this collating sequence.
+    pub fn compare(&self, a: &Column, b: &Column) -> Ordering {
+        match (self, a, b) {
+            (Collation::NoCase, Column::Str(a), Column::Str(b)) => a
+                .bytes()
+                .map(|c| c.to_ascii_lowercase())
+                .cmp(b.bytes().map(|c| c.to_ascii_lowercase())),
+            (Collation::RTrim, Column::Str(a), Column::Str(b)) => {
+                a.trim_end_matches(' ').cmp(b.trim_end_matches(' '))
+            }
+            (_, a, b) => a.compare(b),
         }
-        Ok(CreateTableQuery { column_orders })
     }
 }
 
@@ -111,51 +614,216 @@ pub struct CreateIdxQuery {
     pub idx_name: String,
     #[allow(dead_code)]
     pub table_name: String,
-    pub columns: HashSet<String>,
+    // indexed columns in index order
+    pub columns: Vec<IndexedColumn>,
+    // the condition of a partial index
+    pub where_clause: Option<Expr>,
 }
 
 impl CreateIdxQuery {
     pub fn from_sql(sql: &str) -> anyhow::Result<CreateIdxQuery> {
-        let re = Regex::new(
-            r#"CREATE INDEX (?P<idx_name>.+)\s+on (?P<table_name>.+) ((?P<columns>.+))"#,
-        )
-        .unwrap();
+        let mut idx = match parser::parse(sql)? {
+            Statement::CreateIndex(idx) => idx,
+            _ => return Err(anyhow!("can't parse create index query from {}", sql)),
+        };
+        idx.where_clause.iter_mut().try_for_each(fold_expr)?;
 
-        let caps = re
-            .captures(sql)
-            .ok_or(anyhow!("can't parse create index query from {}", sql))?;
-        let idx_name = caps["idx_name"].to_string();
-        let table_name = caps["table_name"].to_string();
-        let columns_str = caps["columns"].trim_matches('(').trim_matches(')');
+        Ok(CreateIdxQuery {
+            idx_name: idx.name,
+            table_name: idx.table_name,
+            columns: fold_columns(idx.columns),
+            where_clause: idx.where_clause,
+        })
+    }
+}
 
-        let mut columns = HashSet::new();
-        for c in columns_str.split(',') {
-            columns.insert(c.to_string());
-        }
+#[derive(Debug)]
+pub struct CreateViewQuery {
+    #[allow(dead_code)]
+    pub view_name: String,
+    // explicit column names, if given as CREATE VIEW v(a, b) AS ...
+    pub columns: Option<Vec<String>>,
+    pub select_sql: String,
+}
 
-        Ok(CreateIdxQuery {
-            idx_name,
-            table_name,
-            columns,
+impl CreateViewQuery {
+    pub fn from_sql(sql: &str) -> anyhow::Result<CreateViewQuery> {
+        let view = match parser::parse(sql)? {
+            Statement::CreateView(view) => view,
+            _ => return Err(anyhow!("can't parse create view query from {}", sql)),
+        };
+
+        Ok(CreateViewQuery {
+            view_name: view.name,
+            columns: view
+                .columns
+                .map(|columns| columns.iter().map(|c| c.to_ascii_lowercase()).collect()),
+            select_sql: view.select_sql,
         })
     }
 }
 
+#[allow(clippy::enum_variant_names)]
 pub enum CreateQuery {
     CreateIdx(CreateIdxQuery),
     CreateTable(CreateTableQuery),
+    CreateView(CreateViewQuery),
+    CreateTrigger,
 }
 
 impl CreateQuery {
-    pub fn from_sql(sql: &str) -> anyhow::Result<CreateQuery> {
-        match sql {
-            s if s.starts_with("CREATE TABLE") => {
-                CreateTableQuery::from_sql(sql).map(CreateQuery::CreateTable)
-            }
-            s if s.starts_with("CREATE INDEX") => {
-                CreateIdxQuery::from_sql(sql).map(CreateQuery::CreateIdx)
-            }
-            _ => todo!("can't parse create query {sql}"),
+    /// Parses the sql of a sqlite_schema row of the given type.
+    pub fn from_sql(object_type: &str, sql: &str) -> anyhow::Result<CreateQuery> {
+        match object_type {
+            "table" => CreateTableQuery::from_sql(sql).map(CreateQuery::CreateTable),
+            "index" => CreateIdxQuery::from_sql(sql).map(CreateQuery::CreateIdx),
+            "view" => CreateViewQuery::from_sql(sql).map(CreateQuery::CreateView),
+            // trigger programs are only kept as sql
+            "trigger" => Ok(CreateQuery::CreateTrigger),
+            _ => Err(anyhow!("unknown schema object type {object_type}")),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indexed(names: &[&str]) -> Vec<IndexedColumn> {
+        names
+            .iter()
+            .map(|name| IndexedColumn {
+                name: name.to_string(),
+                descending: false,
+                collation: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn folds_identifiers() {
+        let query = SelectQuery::from_query_string(
+            r#"SELECT Id, "My Col" FROM "My Table" AS T WHERE t.VAL = 'A' ORDER BY Val"#,
+        )
+        .unwrap();
+        assert_eq!(query.table_name, "my table");
+        assert_eq!(query.alias.as_deref(), Some("t"));
+        assert_eq!(query.columns, [Expr::column("id"), Expr::column("my col")]);
+        assert_eq!(
+            query.where_clause,
+            Some(Expr::Binary {
+                op: BinaryOp::Eq,
+                lhs: Box::new(Expr::column("val")),
+                rhs: Box::new(Expr::Literal(Literal::Text("A".to_string()))),
+            })
+        );
+        assert_eq!(query.order_by[0].expr, Expr::column("val"));
+
+        let table = CreateTableQuery::from_sql(
+            "CREATE TABLE T (Id INTEGER PRIMARY KEY, Val TEXT, UNIQUE(VAL))",
+        )
+        .unwrap();
+        assert_eq!(
+            table.column_orders,
+            BTreeMap::from([("id".to_string(), 0), ("val".to_string(), 1)])
+        );
+        assert_eq!(table.rowid_alias.as_deref(), Some("id"));
+        assert_eq!(table.autoindex_columns, [indexed(&["val"])]);
+    }
+
+    #[test]
+    fn derives_autoindexes() {
+        // a constraint on the same columns as an earlier one gets no index of its own
+        let table = CreateTableQuery::from_sql(
+            "CREATE TABLE t (a unique, b unique, c text primary key, unique(a), unique(b, a))",
+        )
+        .unwrap();
+        assert_eq!(
+            table.autoindex_columns,
+            [
+                indexed(&["a"]),
+                indexed(&["b"]),
+                indexed(&["c"]),
+                indexed(&["b", "a"])
+            ]
+        );
+
+        let table =
+            CreateTableQuery::from_sql("CREATE TABLE u (x int, y, primary key(x), unique(x))")
+                .unwrap();
+        assert_eq!(table.autoindex_columns, [indexed(&["x"])]);
+
+        // INTEGER PRIMARY KEY is the rowid, only the UNIQUE constraint gets an index
+        let table =
+            CreateTableQuery::from_sql("CREATE TABLE v (x integer, y, primary key(x), unique(x))")
+                .unwrap();
+        assert_eq!(table.autoindex_columns, [indexed(&["x"])]);
+
+        // the primary key of a WITHOUT ROWID table has a number but no index of its own
+        let table = CreateTableQuery::from_sql(
+            "CREATE TABLE w (a text, b text unique, c, primary key (a), unique(c), unique(a)) WITHOUT ROWID",
+        )
+        .unwrap();
+        assert_eq!(
+            table.autoindex_columns,
+            [indexed(&["b"]), indexed(&["a"]), indexed(&["c"])]
+        );
+
+        // columns only share an index with the same collating sequence, which is the
+        // column's own if not given
+        let table = CreateTableQuery::from_sql(
+            "CREATE TABLE c (a unique, b text collate NoCase unique, unique(a collate nocase), \
+             unique(b), unique(b collate binary), unique(a desc))",
+        )
+        .unwrap();
+        assert_eq!(
+            table
+                .autoindex_columns
+                .iter()
+                .map(|columns| (columns[0].name.as_str(), columns[0].collation.as_deref()))
+                .collect::<Vec<_>>(),
+            [
+                ("a", None),
+                ("b", Some("nocase")),
+                ("a", Some("nocase")),
+                ("b", Some("binary"))
+            ]
+        );
+    }
+
+    #[test]
+    fn orders_without_rowid_columns() {
+        // records hold the primary key first, then the other columns in table order
+        let table = CreateTableQuery::from_sql(
+            "CREATE TABLE w (a text collate NOCASE, b int, c text, d, primary key (c desc, a)) WITHOUT ROWID",
+        )
+        .unwrap();
+        assert_eq!(
+            table.column_orders,
+            BTreeMap::from([
+                ("c".to_string(), 0),
+                ("a".to_string(), 1),
+                ("b".to_string(), 2),
+                ("d".to_string(), 3)
+            ])
+        );
+        assert_eq!(
+            table.primary_key,
+            [
+                IndexedColumn {
+                    name: "c".to_string(),
+                    descending: true,
+                    collation: None,
+                },
+                IndexedColumn {
+                    name: "a".to_string(),
+                    descending: false,
+                    collation: Some("nocase".to_string()),
+                }
+            ]
+        );
+        assert!(table.without_rowid);
+        assert_eq!(table.autoindex_columns.len(), 1);
+        assert_eq!(table.autoindex_columns[0], table.primary_key);
+    }
+}
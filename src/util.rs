@@ -1,6 +1,8 @@
 use core::panic;
 
-use crate::page::ColumnType;
+use anyhow::{bail, Result};
+
+use crate::page::{Column, ColumnType};
 
 pub fn read_varint(bytes: &[u8]) -> (i64, u8) {
     if bytes.len() > 9 {
@@ -39,40 +41,114 @@ pub fn read_varint(bytes: &[u8]) -> (i64, u8) {
     (res, trimmed_bytes.len().try_into().unwrap())
 }
 
-pub fn get_content_size_type(input: i64) -> (u64, ColumnType) {
+pub fn get_content_size_type(input: i64) -> Result<(u64, ColumnType)> {
     if input == 0 {
-        return (0, ColumnType::Null);
+        return Ok((0, ColumnType::Null));
     }
 
     if input == 1 {
-        return (1, ColumnType::I8);
+        return Ok((1, ColumnType::I8));
     }
 
     if input == 2 {
-        return (2, ColumnType::I16);
+        return Ok((2, ColumnType::I16));
     }
 
     if input == 3 {
-        return (3, ColumnType::I24);
+        return Ok((3, ColumnType::I24));
+    }
+
+    if input == 4 {
+        return Ok((4, ColumnType::I32));
+    }
+
+    if input == 5 {
+        return Ok((6, ColumnType::I48));
+    }
+
+    if input == 6 {
+        return Ok((8, ColumnType::I64));
+    }
+
+    if input == 7 {
+        return Ok((8, ColumnType::F64));
     }
 
     if input == 8 {
-        return (0, ColumnType::Zero);
+        return Ok((0, ColumnType::Zero));
     }
 
     if input == 9 {
-        return (0, ColumnType::One);
+        return Ok((0, ColumnType::One));
     }
 
-    //if input >= 12 && input % 2 == 0 {
-    //    return (((input - 12) / 2).try_into().unwrap(), ColumnType::Blob);
-    //}
+    if input >= 12 && input % 2 == 0 {
+        return Ok((((input - 12) / 2).try_into().unwrap(), ColumnType::Blob));
+    }
 
     if input >= 13 && input % 2 == 1 {
-        return (((input - 13) / 2).try_into().unwrap(), ColumnType::Str);
+        return Ok((((input - 13) / 2).try_into().unwrap(), ColumnType::Str));
     }
 
-    todo!("column type {input}")
+    bail!("invalid serial type {input}")
+}
+
+/// Parses text that is a well-formed integer or real literal, ignoring surrounding
+/// spaces, like SQLite does when it applies numeric affinity.
+pub fn parse_numeric(s: &str) -> Option<Column> {
+    let s = s.trim();
+    // Rust also parses words like "inf" and "NaN"
+    if !s.bytes().any(|b| b.is_ascii_digit())
+        || !s
+            .bytes()
+            .all(|b| b.is_ascii_digit() || b"+-.eE".contains(&b))
+    {
+        return None;
+    }
+
+    match s.parse::<i64>() {
+        Ok(i) => Some(Column::I64(i)),
+        Err(_) => s.parse::<f64>().ok().map(Column::F64),
+    }
+}
+
+/// Formats a REAL the way sqlite3 prints it, i.e. printf's `%!.15g`:
+/// 15 significant digits without trailing zeros, but always with a decimal point.
+pub fn format_real(f: f64) -> String {
+    if f.is_infinite() {
+        return String::from(if f > 0.0 { "Inf" } else { "-Inf" });
+    }
+    if f == 0.0 {
+        return String::from("0.0");
+    }
+
+    // d.dddddddddddddde<exp>, already rounded to 15 significant digits
+    let sci = format!("{:.14e}", f.abs());
+    let (mantissa, exp) = sci.split_once('e').unwrap();
+    let exp: i32 = exp.parse().unwrap();
+    let digits = mantissa.replace('.', "");
+    let sign = if f < 0.0 { "-" } else { "" };
+
+    if !(-4..15).contains(&exp) {
+        let (int_part, frac_part) = digits.split_at(1);
+        let frac_part = frac_part.trim_end_matches('0');
+        let frac_part = if frac_part.is_empty() { "0" } else { frac_part };
+        let exp_sign = if exp < 0 { '-' } else { '+' };
+        return format!("{sign}{int_part}.{frac_part}e{exp_sign}{:02}", exp.abs());
+    }
+
+    let (int_part, frac_part) = if exp >= 0 {
+        let (i, f) = digits.split_at(exp as usize + 1);
+        (i.to_string(), f.to_string())
+    } else {
+        (
+            String::from("0"),
+            "0".repeat((-exp - 1) as usize) + digits.as_str(),
+        )
+    };
+    let frac_part = frac_part.trim_end_matches('0');
+    let frac_part = if frac_part.is_empty() { "0" } else { frac_part };
+    format!("{sign}{int_part}.{frac_part}")
 }
 
 #[cfg(test)]
@@ -121,4 +197,35 @@ mod tests {
         assert_eq!(result, 398356367593959435);
         assert_eq!(n, 9);
     }
+
+    #[test]
+    fn rejects_invalid_serial_types() {
+        assert!(matches!(get_content_size_type(7), Ok((8, ColumnType::F64))));
+        assert!(matches!(
+            get_content_size_type(15),
+            Ok((1, ColumnType::Str))
+        ));
+        for serial_type in [10, 11, -1] {
+            assert_eq!(
+                get_content_size_type(serial_type).unwrap_err().to_string(),
+                format!("invalid serial type {serial_type}")
+            );
+        }
+    }
+
+    #[test]
+    fn formats_reals_like_sqlite() {
+        assert_eq!(format_real(0.1), "0.1");
+        assert_eq!(format_real(100.0), "100.0");
+        assert_eq!(format_real(-2.5), "-2.5");
+        assert_eq!(format_real(0.0001), "0.0001");
+        assert_eq!(format_real(1e-5), "1.0e-05");
+        assert_eq!(format_real(1e14), "100000000000000.0");
+        assert_eq!(format_real(1e15), "1.0e+15");
+        assert_eq!(format_real(1e100), "1.0e+100");
+        assert_eq!(format_real(3.0 / 7.0), "0.428571428571429");
+        assert_eq!(format_real(123456789012345678.0), "1.23456789012346e+17");
+        assert_eq!(format_real(123456789012345.6), "123456789012346.0");
+        assert_eq!(format_real(f64::INFINITY), "Inf");
+    }
 }
@@ -0,0 +1,65 @@
+use std::fmt::Display;
+
+use crate::{page::Column, util::format_real};
+
+/// A value of a result row, with the storage class SQLite gives it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl From<Column> for Value {
+    fn from(column: Column) -> Self {
+        match column {
+            Column::Null => Value::Null,
+            Column::F64(r) => Value::Real(r),
+            Column::Str(s) => Value::Text(s),
+            Column::Blob(b) => Value::Blob(b),
+            c => Value::Integer(c.as_i64().unwrap()),
+        }
+    }
+}
+
+/// Prints values like sqlite3 does in list mode, where NULL is empty. Blobs that
+/// aren't UTF-8 text can only be written raw, not printed; with the alternate flag
+/// (`{:#}`) they are printed as `X'..'` hex literals, like in quote mode.
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Integer(i) => write!(f, "{}", i),
+            Value::Real(r) => write!(f, "{}", format_real(*r)),
+            Value::Text(s) => write!(f, "{}", s),
+            Value::Blob(b) if f.alternate() => {
+                write!(f, "X'")?;
+                for byte in b {
+                    write!(f, "{:02x}", byte)?;
+                }
+                write!(f, "'")
+            }
+            Value::Blob(b) => write!(f, "{}", String::from_utf8_lossy(b)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        assert_eq!(Value::from(Column::I8(-3)), Value::Integer(-3));
+        assert_eq!(Value::from(Column::One), Value::Integer(1));
+        assert_eq!(Value::from(Column::F64(1.0)).to_string(), "1.0");
+        assert_eq!(Value::from(Column::Null).to_string(), "");
+        assert_eq!(
+            Value::from(Column::Str("abc".to_string())),
+            Value::Text("abc".to_string())
+        );
+        assert_eq!(format!("{:#}", Value::Blob(vec![0x41, 0xff])), "X'41ff'");
+    }
+}
@@ -0,0 +1,102 @@
+-- The database the tests in src/db.rs read, made with
+--   rm -f test.db && sqlite3 -unsafe-testing test.db < test.sql
+pragma page_size = 512;
+.filectrl reserve_bytes 8
+
+-- the long body spills over several overflow pages
+create table notes (id integer primary key, title text, body text);
+insert into notes values (1, 'short', 'hello');
+insert into notes
+select 2, 'long', group_concat(printf('%04d', value), ' ') from generate_series(1, 600);
+
+-- thirty more tables push sqlite_schema past page 1
+create table t01 (id integer primary key, name text not null, value real, note text default 'none');
+create table t02 (id integer primary key, name text not null, value real, note text default 'none');
+create table t03 (id integer primary key, name text not null, value real, note text default 'none');
+create table t04 (id integer primary key, name text not null, value real, note text default 'none');
+create table t05 (id integer primary key, name text not null, value real, note text default 'none');
+create table t06 (id integer primary key, name text not null, value real, note text default 'none');
+create table t07 (id integer primary key, name text not null, value real, note text default 'none');
+create table t08 (id integer primary key, name text not null, value real, note text default 'none');
+create table t09 (id integer primary key, name text not null, value real, note text default 'none');
+create table t10 (id integer primary key, name text not null, value real, note text default 'none');
+create table t11 (id integer primary key, name text not null, value real, note text default 'none');
+create table t12 (id integer primary key, name text not null, value real, note text default 'none');
+create table t13 (id integer primary key, name text not null, value real, note text default 'none');
+create table t14 (id integer primary key, name text not null, value real, note text default 'none');
+create table t15 (id integer primary key, name text not null, value real, note text default 'none');
+create table t16 (id integer primary key, name text not null, value real, note text default 'none');
+create table t17 (id integer primary key, name text not null, value real, note text default 'none');
+create table t18 (id integer primary key, name text not null, value real, note text default 'none');
+create table t19 (id integer primary key, name text not null, value real, note text default 'none');
+create table t20 (id integer primary key, name text not null, value real, note text default 'none');
+create table t21 (id integer primary key, name text not null, value real, note text default 'none');
+create table t22 (id integer primary key, name text not null, value real, note text default 'none');
+create table t23 (id integer primary key, name text not null, value real, note text default 'none');
+create table t24 (id integer primary key, name text not null, value real, note text default 'none');
+create table t25 (id integer primary key, name text not null, value real, note text default 'none');
+create table t26 (id integer primary key, name text not null, value real, note text default 'none');
+create table t27 (id integer primary key, name text not null, value real, note text default 'none');
+create table t28 (id integer primary key, name text not null, value real, note text default 'none');
+create table t29 (id integer primary key, name text not null, value real, note text default 'none');
+create table t30 (id integer primary key, name text not null, value real, note text default 'none');
+insert into t30 values (1, 'last', 0.5, 'x');
+
+-- a table with an index on each of two columns, of several pages each
+create table people (id integer primary key, name text, country text, city text, age int);
+create index idx_people_country on people (country);
+create index idx_people_age on people (age);
+insert into people
+select value, 'p' || value,
+       case value % 5 when 0 then 'br' when 1 then 'de' when 2 then 'fr' when 3 then 'jp' else 'us' end,
+       'c' || (value % 7),
+       case when value % 10 = 0 then null else 18 + value % 50 end
+from generate_series(1, 500);
+
+-- a table with an index on two columns
+create table trips (id integer primary key, country text, city text, year int);
+create index idx_trips_country_city on trips (country, city);
+insert into trips
+select value,
+       case value % 4 when 0 then 'de' when 1 then 'fr' when 2 then 'jp' else 'us' end,
+       'c' || (value % 6),
+       2000 + value % 20
+from generate_series(1, 400);
+
+-- an index with its own collating sequence and a partial index
+create table tags (id integer primary key, name text, score int);
+create index idx_tags_name on tags (name collate nocase);
+create index idx_tags_score on tags (score) where score >= 50;
+insert into tags values
+  (1, 'apple', 10), (2, 'Banana', 60), (3, 'cherry', 80), (4, 'APPLE', 55), (5, 'banana', 20),
+  (6, 'Date', 90);
+
+-- a WITHOUT ROWID table of several pages, keyed by a NOCASE and a descending column
+create table events (
+  kind text collate nocase, seq int, note text, primary key (kind, seq desc)
+) without rowid;
+insert into events
+select case value % 3 when 0 then 'Alpha' when 1 then 'beta' else 'GAMMA' end, value, 'e' || value
+from generate_series(1, 300);
+
+-- the primary key takes the first automatic index number, label the second
+create table codes (code text primary key, label text unique) without rowid;
+insert into codes values ('b', 'bee'), ('a', 'ay'), ('c', 'sea');
+
+-- rows and keys of about 60 bytes, which make b-trees of three levels and more
+create table deep (id integer primary key, pad text);
+create index idx_deep_pad on deep (pad);
+insert into deep select value, printf('%04d%056d', value, 0) from generate_series(1, 800);
+
+-- a table and an index without entries
+create table blank (x);
+create index idx_blank_x on blank (x);
+
+-- views of people and trips, one with its own column names, one grouping its rows
+-- and one with its own LIMIT
+create view young (pid, pname) as select id, name from people where age < 25;
+create view countries as select country, count(*) as n from people group by country;
+create view first_trips as select id, year from trips order by id limit 3;
+
+-- the leaf of notes ends in a marker in its reserved bytes
+update sqlite_dbpage set data = unhex(hex(substr(data, 1, 504)) || hex('reserved')) where pgno = 2;